@@ -1,20 +1,107 @@
 use aoc2022_rust::problem;
+use clap::Parser;
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+
+/// Simulate a rope moving across a grid
+#[derive(Parser, Debug)]
+struct Cli {
+    /// Number of knots in the rope, including the head
+    #[arg(short, long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(2..))]
+    knots: u32,
+    /// Print the number of positions visited by every knot, not just the puzzle answers
+    #[arg(short, long)]
+    all_knots: bool,
+    /// How each knot follows the one in front of it
+    #[arg(short, long, value_enum, default_value_t = RuleKind::Puzzle)]
+    rule: RuleKind,
+    /// How far a knot can be from the one in front before it moves, for the elastic rule
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(i32).range(1..))]
+    slack: i32,
+    /// Use the test input instead of the puzzle input
+    #[arg(short, long)]
+    test: bool,
+    /// Animate the rope in the terminal
+    #[arg(long)]
+    animate: bool,
+    /// Write the animation to an animated GIF
+    #[arg(long)]
+    gif: Option<PathBuf>,
+    /// Write the animation to a directory of numbered PNG files
+    #[arg(long)]
+    png_dir: Option<PathBuf>,
+    /// Milliseconds between animation frames
+    #[arg(long, default_value_t = 50)]
+    delay: u64,
+    /// Number of head steps to take between animation frames
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    steps_per_frame: u32,
+    /// Maximum width of the terminal animation in characters
+    #[arg(long, default_value_t = 120)]
+    max_width: usize,
+    /// Maximum height of the terminal animation in characters
+    #[arg(long, default_value_t = 50)]
+    max_height: usize,
+    /// Size in pixels of one grid cell in GIF and PNG output
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+    cell_size: u32,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum RuleKind {
+    Puzzle,
+    Orthogonal,
+    Elastic,
+}
 
 fn main() {
-    let input = problem::load(09);
-    let solution_1 = solve1(&input);
-    println!("Puzzle 1 {}", solution_1);
-    let solution_2 = solve2(&input);
-    println!("Puzzle 2 {}", solution_2);
+    let args = Cli::parse();
+    let input = if args.test { problem::load_test(9) } else { problem::load(9) };
+    let n_knots = args.knots as usize;
+    let rule: Rc<dyn FollowRule> = match args.rule {
+        RuleKind::Puzzle => Rc::new(PuzzleRule),
+        RuleKind::Orthogonal => Rc::new(OrthogonalRule),
+        RuleKind::Elastic => Rc::new(ElasticRule { slack: args.slack }),
+    };
+    let options = AnimationOptions {
+        steps_per_frame: args.steps_per_frame as usize,
+        delay: Duration::from_millis(args.delay),
+    };
+    if args.animate {
+        animate(&input, n_knots, &rule, &options, args.max_width, args.max_height);
+    }
+    if let Some(path) = &args.gif {
+        write_gif(&input, n_knots, &rule, &options, args.cell_size as usize, path);
+    }
+    if let Some(dir) = &args.png_dir {
+        write_pngs(&input, n_knots, &rule, &options, args.cell_size as usize, dir);
+    }
+    let visited = solve(&input, n_knots, rule);
+    if args.all_knots {
+        for (knot, count) in visited.iter().enumerate() {
+            println!("Knot {} {}", knot, count);
+        }
+    } else {
+        // The first knot behind the head moves the same regardless of the rope length
+        // so both puzzles come out of a single run
+        println!("Puzzle 1 {}", visited[1]);
+        println!("Puzzle 2 {}", visited[visited.len() - 1]);
+    }
 }
 
 #[derive(Debug)]
 enum Direction {
     Right,
     Up,
+    UpRight,
+    UpLeft,
 }
 
 #[derive(Debug)]
@@ -30,44 +117,271 @@ impl Move {
             distance,
         }
     }
+
+    // The change in the head's position for each step of this move
+    fn step(&self) -> (i32, i32) {
+        let sign = self.distance.signum();
+        match self.direction {
+            Direction::Right => (sign, 0),
+            Direction::Up => (0, sign),
+            Direction::UpRight => (sign, sign),
+            Direction::UpLeft => (-sign, sign),
+        }
+    }
+}
+
+// Inclusive bounding box of every position the head passes through
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Bounds {
+    min: (i32, i32),
+    max: (i32, i32),
+}
+
+impl Bounds {
+    fn n_cells(&self) -> usize {
+        let width = (self.max.0 - self.min.0) as usize + 1;
+        let height = (self.max.1 - self.min.1) as usize + 1;
+        width.saturating_mul(height)
+    }
+}
+
+fn head_bounds(moves: &[Move]) -> Bounds {
+    let mut head = (0, 0);
+    let mut bounds = Bounds { min: head, max: head };
+    for movement in moves {
+        let step = movement.step();
+        head.0 += step.0 * movement.distance.abs();
+        head.1 += step.1 * movement.distance.abs();
+        bounds.min = (bounds.min.0.min(head.0), bounds.min.1.min(head.1));
+        bounds.max = (bounds.max.0.max(head.0), bounds.max.1.max(head.1));
+    }
+    bounds
+}
+
+// Above this many cells across all of a rope's bit grids they cost more memory
+// than they're worth so we fall back to hashing the visited positions
+const MAX_DENSE_CELLS: usize = 1 << 28;
+
+// Set of visited positions. Knots only ever move towards the knot in front
+// (see FollowRule) so no knot can leave the box the head moves in, which means we can
+// use a flat bit grid over the head's bounds when we know them.
+enum Visited {
+    Sparse(HashSet<(i32, i32)>),
+    Dense {
+        bounds: Bounds,
+        width: usize,
+        bits: Vec<u64>,
+        count: usize,
+    },
+}
+
+impl Visited {
+    fn sparse() -> Self {
+        Visited::Sparse(HashSet::new())
+    }
+
+    // One of `grids` sets over the same bounds, which share the dense cell budget
+    fn for_bounds(bounds: Bounds, grids: usize) -> Self {
+        let n_cells = bounds.n_cells();
+        if n_cells.saturating_mul(grids) > MAX_DENSE_CELLS {
+            return Visited::sparse();
+        }
+        Visited::Dense {
+            bounds,
+            width: (bounds.max.0 - bounds.min.0) as usize + 1,
+            bits: vec![0; n_cells.div_ceil(64)],
+            count: 0,
+        }
+    }
+
+    fn insert(&mut self, location: (i32, i32)) {
+        match self {
+            Visited::Sparse(set) => {
+                set.insert(location);
+            }
+            Visited::Dense { bounds, width, bits, count } => {
+                let index = (location.1 - bounds.min.1) as usize * *width +
+                    (location.0 - bounds.min.0) as usize;
+                let mask = 1 << (index % 64);
+                if bits[index / 64] & mask == 0 {
+                    bits[index / 64] |= mask;
+                    *count += 1;
+                }
+            }
+        }
+    }
+
+    fn contains(&self, location: (i32, i32)) -> bool {
+        match self {
+            Visited::Sparse(set) => set.contains(&location),
+            Visited::Dense { bounds, width, bits, .. } => {
+                if location.0 < bounds.min.0 || location.0 > bounds.max.0 ||
+                    location.1 < bounds.min.1 || location.1 > bounds.max.1 {
+                    return false;
+                }
+                let index = (location.1 - bounds.min.1) as usize * *width +
+                    (location.0 - bounds.min.0) as usize;
+                bits[index / 64] & (1 << (index % 64)) != 0
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Visited::Sparse(set) => set.len(),
+            Visited::Dense { count, .. } => *count,
+        }
+    }
 }
 
 struct Rope {
     n_knots: usize,
     knot_locations: Vec<(i32, i32)>,
-    tail_visited: HashSet<(i32, i32)>,
+    // Positions visited by each knot when tracking all knots, otherwise just the tail's
+    visited: Vec<Visited>,
+    // Bounds of the head's path if known up front, lets us track visits densely
+    bounds: Option<Bounds>,
+    rule: Rc<dyn FollowRule>,
 }
 
 impl Rope {
-    fn new(n_knots: i32) -> Self {
-        let mut knot_locations: Vec<(i32, i32)> = vec![];
-        for _ in 0..n_knots {
-            knot_locations.push((0, 0));
-        }
-        let tail_visited = HashSet::from([knot_locations[n_knots as usize - 1]]);
+    fn new(n_knots: usize) -> Self {
+        assert!(n_knots > 0, "Rope must have at least one knot");
+        let knot_locations: Vec<(i32, i32)> = vec![(0, 0); n_knots];
+        let mut tail_visited = Visited::sparse();
+        tail_visited.insert(knot_locations[n_knots - 1]);
         Rope {
-            n_knots: n_knots as usize,
+            n_knots,
             knot_locations,
-            tail_visited
+            visited: vec![tail_visited],
+            bounds: None,
+            rule: Rc::new(PuzzleRule),
         }
     }
 
+    fn with_rule(mut self, rule: Rc<dyn FollowRule>) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    fn with_bounds(n_knots: usize, bounds: Bounds) -> Self {
+        let mut rope = Rope::new(n_knots);
+        rope.bounds = Some(bounds);
+        rope.visited = vec![rope.new_visited(rope.knot_locations[n_knots - 1], 1)];
+        rope
+    }
+
+    fn track_all_knots(mut self) -> Self {
+        self.visited = self.knot_locations
+            .iter()
+            .map(|&location| self.new_visited(location, self.n_knots))
+            .collect();
+        self
+    }
+
+    fn new_visited(&self, start: (i32, i32), grids: usize) -> Visited {
+        let mut visited = match self.bounds {
+            Some(bounds) => Visited::for_bounds(bounds, grids),
+            None => Visited::sparse(),
+        };
+        visited.insert(start);
+        visited
+    }
+
+    fn tracks_all_knots(&self) -> bool {
+        self.visited.len() == self.n_knots
+    }
+
+    fn tail_visited(&self) -> &Visited {
+        self.visited.last().unwrap()
+    }
+
+    fn visited_counts(&self) -> Vec<usize> {
+        self.visited.iter().map(|knot| knot.len()).collect()
+    }
+
     fn perform_move(&mut self, movement: Move) -> () {
+        self.perform_move_observed(movement, |_| ());
+    }
+
+    // As perform_move but calls on_step with the rope after every step of the head
+    fn perform_move_observed(&mut self, movement: Move, mut on_step: impl FnMut(&Rope)) {
+        let step = movement.step();
         for _ in 0..movement.distance.abs() {
-            match movement.direction {
-                Direction::Right => self.knot_locations[0].0 += movement.distance.signum(),
-                Direction::Up => self.knot_locations[0].1 += movement.distance.signum()
-            }
+            self.knot_locations[0].0 += step.0;
+            self.knot_locations[0].1 += step.1;
             for knot in 1..self.n_knots {
-                self.knot_locations[knot] = update_knot_position(self.knot_locations[knot],
-                                     self.knot_locations[knot - 1])
+                self.knot_locations[knot] = self.rule.follow(self.knot_locations[knot],
+                                                             self.knot_locations[knot - 1])
             }
             self.update_state();
+            on_step(self);
         }
     }
 
     fn update_state(&mut self) {
-        self.tail_visited.insert(self.knot_locations[self.n_knots - 1]);
+        if self.tracks_all_knots() {
+            for (knot, location) in self.visited.iter_mut().zip(self.knot_locations.iter()) {
+                knot.insert(*location);
+            }
+        } else {
+            self.visited[0].insert(self.knot_locations[self.n_knots - 1]);
+        }
+    }
+}
+
+// How a knot moves in response to the knot in front of it moving. Implementations
+// must only ever move a knot towards its leader, never past it, so that every knot
+// stays within the bounds of the head's path.
+trait FollowRule {
+    fn follow(&self, knot_update: (i32, i32), knot_follow: (i32, i32)) -> (i32, i32);
+}
+
+// The rule from the puzzle, step diagonally towards the leader when not touching
+struct PuzzleRule;
+
+impl FollowRule for PuzzleRule {
+    fn follow(&self, knot_update: (i32, i32), knot_follow: (i32, i32)) -> (i32, i32) {
+        update_knot_position(knot_update, knot_follow)
+    }
+}
+
+// Knots can only move horizontally or vertically, so when not touching the knot
+// closes the larger of the two gaps
+struct OrthogonalRule;
+
+impl FollowRule for OrthogonalRule {
+    fn follow(&self, mut knot_update: (i32, i32), knot_follow: (i32, i32)) -> (i32, i32) {
+        let x_dist = knot_follow.0 - knot_update.0;
+        let y_dist = knot_follow.1 - knot_update.1;
+        if x_dist.abs() <= 1 && y_dist.abs() <= 1 {
+            return knot_update
+        }
+        if x_dist.abs() >= y_dist.abs() {
+            knot_update.0 += x_dist.signum();
+        } else {
+            knot_update.1 += y_dist.signum();
+        }
+        knot_update
+    }
+}
+
+// Knots stay put until the leader is more than slack away on either axis, then step
+// diagonally towards it like the puzzle rule. A slack of 1 is the puzzle rule.
+struct ElasticRule {
+    slack: i32,
+}
+
+impl FollowRule for ElasticRule {
+    fn follow(&self, mut knot_update: (i32, i32), knot_follow: (i32, i32)) -> (i32, i32) {
+        let x_dist = knot_follow.0 - knot_update.0;
+        let y_dist = knot_follow.1 - knot_update.1;
+        if x_dist.abs() <= self.slack && y_dist.abs() <= self.slack {
+            return knot_update
+        }
+        knot_update.0 += x_dist.signum();
+        knot_update.1 += y_dist.signum();
+        knot_update
     }
 }
 
@@ -97,7 +411,7 @@ fn update_knot_position(mut knot_update: (i32, i32), knot_follow: (i32, i32)) ->
 
 fn parse_moves(input: &str) -> Vec<Move> {
     lazy_static! {
-        static ref RE: Regex = Regex::new(r"(?P<direction>[RLUD]) (?P<distance>\d+)").unwrap();
+        static ref RE: Regex = Regex::new(r"(?P<direction>UR|UL|DR|DL|[RLUD]) (?P<distance>\d+)").unwrap();
     }
     let mut moves: Vec<Move> = vec![];
     for capture in RE.captures_iter(input) {
@@ -107,39 +421,377 @@ fn parse_moves(input: &str) -> Vec<Move> {
             "L" => Move::new(Direction::Right, -distance),
             "U" => Move::new(Direction::Up, distance),
             "D" => Move::new(Direction::Up, -distance),
+            "UR" => Move::new(Direction::UpRight, distance),
+            "DL" => Move::new(Direction::UpRight, -distance),
+            "UL" => Move::new(Direction::UpLeft, distance),
+            "DR" => Move::new(Direction::UpLeft, -distance),
             _ => panic!("Found invalid direction"),
         })
     }
     moves
 }
 
-fn solve1(input: &str) -> usize {
+// Returns the number of positions visited by each knot, indexed from the head
+fn solve(input: &str, n_knots: usize, rule: Rc<dyn FollowRule>) -> Vec<usize> {
     let moves: Vec<Move> = parse_moves(input);
-    let mut rope: Rope = Rope::new(2);
+    let mut rope: Rope = Rope::with_bounds(n_knots, head_bounds(&moves))
+        .with_rule(rule)
+        .track_all_knots();
     for movement in moves {
         rope.perform_move(movement);
     }
-    return rope.tail_visited.len();
+    rope.visited_counts()
 }
 
-fn solve2(input: &str) -> usize {
-    let moves: Vec<Move> = parse_moves(input);
-    let mut rope: Rope = Rope::new(10);
+struct AnimationOptions {
+    steps_per_frame: usize,
+    delay: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Pixel {
+    Empty,
+    Visited,
+    Knot(usize),
+}
+
+// Maps the head's bounding box onto a grid of at most max_width x max_height
+// cells, each cell covering scale x scale positions when the motion is too big
+// to show one to one.
+struct Viewport {
+    bounds: Bounds,
+    scale: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Viewport {
+    fn fit(bounds: Bounds, max_width: usize, max_height: usize) -> Self {
+        let grid_width = (bounds.max.0 - bounds.min.0) as usize + 1;
+        let grid_height = (bounds.max.1 - bounds.min.1) as usize + 1;
+        let scale = grid_width.div_ceil(max_width.max(1))
+            .max(grid_height.div_ceil(max_height.max(1)))
+            .max(1);
+        Viewport {
+            bounds,
+            scale,
+            width: grid_width.div_ceil(scale),
+            height: grid_height.div_ceil(scale),
+        }
+    }
+
+    // Up is positive y, so row 0 of the picture is the top of the bounds
+    fn cell(&self, location: (i32, i32)) -> (usize, usize) {
+        ((location.0 - self.bounds.min.0) as usize / self.scale,
+         (self.bounds.max.1 - location.1) as usize / self.scale)
+    }
+
+    fn render(&self, rope: &Rope) -> Vec<Vec<Pixel>> {
+        let mut frame = vec![vec![Pixel::Empty; self.width]; self.height];
+        for (row, pixels) in frame.iter_mut().enumerate() {
+            for (col, pixel) in pixels.iter_mut().enumerate() {
+                let visited = (0..self.scale).any(|dy| (0..self.scale).any(|dx| {
+                    let x = self.bounds.min.0 + (col * self.scale + dx) as i32;
+                    let y = self.bounds.max.1 - (row * self.scale + dy) as i32;
+                    rope.tail_visited().contains((x, y))
+                }));
+                if visited {
+                    *pixel = Pixel::Visited;
+                }
+            }
+        }
+        // Draw from the tail forwards so knots nearer the head are on top
+        for (knot, &location) in rope.knot_locations.iter().enumerate().rev() {
+            let (col, row) = self.cell(location);
+            frame[row][col] = Pixel::Knot(knot);
+        }
+        frame
+    }
+}
+
+fn frame_to_string(frame: &[Vec<Pixel>]) -> String {
+    frame.iter()
+        .map(|row| row.iter()
+            .map(|pixel| match pixel {
+                Pixel::Empty => '.',
+                Pixel::Visited => '#',
+                Pixel::Knot(0) => 'H',
+                Pixel::Knot(knot) => char::from_digit(*knot as u32, 10).unwrap_or('*'),
+            })
+            .collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// Background, visited, knot, head and tail colours
+const PALETTE: [u8; 15] = [
+    30, 30, 30,
+    80, 80, 150,
+    200, 200, 200,
+    230, 60, 60,
+    60, 200, 60,
+];
+
+// Expands a frame into palette indices with each cell drawn as a cell_size square
+fn frame_to_indexed(frame: &[Vec<Pixel>], n_knots: usize, cell_size: usize) -> Vec<u8> {
+    let mut pixels = vec![];
+    for row in frame {
+        let indexed_row: Vec<u8> = row.iter()
+            .flat_map(|pixel| {
+                let index = match pixel {
+                    Pixel::Empty => 0,
+                    Pixel::Visited => 1,
+                    Pixel::Knot(0) => 3,
+                    Pixel::Knot(knot) if *knot == n_knots - 1 => 4,
+                    Pixel::Knot(_) => 2,
+                };
+                std::iter::repeat_n(index, cell_size)
+            })
+            .collect();
+        for _ in 0..cell_size {
+            pixels.extend_from_slice(&indexed_row);
+        }
+    }
+    pixels
+}
+
+// Runs the moves through a rope, calling on_frame every steps_per_frame steps of
+// the head plus once at the start and once at the end
+fn simulate_frames(moves: Vec<Move>, n_knots: usize, rule: &Rc<dyn FollowRule>, bounds: Bounds,
+                   steps_per_frame: usize, mut on_frame: impl FnMut(&Rope)) {
+    let mut rope = Rope::with_bounds(n_knots, bounds).with_rule(rule.clone());
+    on_frame(&rope);
+    let mut step = 0;
     for movement in moves {
-        rope.perform_move(movement);
+        rope.perform_move_observed(movement, |rope| {
+            step += 1;
+            if step % steps_per_frame == 0 {
+                on_frame(rope);
+            }
+        });
+    }
+    if step % steps_per_frame != 0 {
+        on_frame(&rope);
     }
-    return rope.tail_visited.len();
+}
+
+fn animate(input: &str, n_knots: usize, rule: &Rc<dyn FollowRule>, options: &AnimationOptions, max_width: usize, max_height: usize) {
+    let moves = parse_moves(input);
+    let bounds = head_bounds(&moves);
+    let viewport = Viewport::fit(bounds, max_width, max_height);
+    let mut stdout = std::io::stdout();
+    simulate_frames(moves, n_knots, rule, bounds, options.steps_per_frame, |rope| {
+        // Clear the screen and move the cursor to the top left before each frame
+        write!(stdout, "\x1b[2J\x1b[H{}\nTail visited {}\n",
+               frame_to_string(&viewport.render(rope)), rope.tail_visited().len())
+            .expect("Failed to write frame");
+        stdout.flush().expect("Failed to flush frame");
+        thread::sleep(options.delay);
+    });
+}
+
+fn write_gif(input: &str, n_knots: usize, rule: &Rc<dyn FollowRule>, options: &AnimationOptions, cell_size: usize, path: &Path) {
+    let moves = parse_moves(input);
+    let bounds = head_bounds(&moves);
+    // A GIF is at most 65535 pixels in each direction
+    let max_cells = u16::MAX as usize / cell_size;
+    let viewport = Viewport::fit(bounds, max_cells, max_cells);
+    let width = (viewport.width * cell_size) as u16;
+    let height = (viewport.height * cell_size) as u16;
+    let file = File::create(path).expect("Failed to create gif file");
+    let mut encoder = gif::Encoder::new(BufWriter::new(file), width, height, &PALETTE)
+        .expect("Failed to write gif header");
+    encoder.set_repeat(gif::Repeat::Infinite).expect("Failed to write gif header");
+    simulate_frames(moves, n_knots, rule, bounds, options.steps_per_frame, |rope| {
+        let pixels = frame_to_indexed(&viewport.render(rope), n_knots, cell_size);
+        let mut frame = gif::Frame::from_indexed_pixels(width, height, pixels, None);
+        // GIF delays are in hundredths of a second
+        frame.delay = (options.delay.as_millis() / 10) as u16;
+        encoder.write_frame(&frame).expect("Failed to write gif frame");
+    });
+}
+
+fn write_pngs(input: &str, n_knots: usize, rule: &Rc<dyn FollowRule>, options: &AnimationOptions, cell_size: usize, dir: &Path) {
+    let moves = parse_moves(input);
+    let bounds = head_bounds(&moves);
+    let viewport = Viewport::fit(bounds, usize::MAX, usize::MAX);
+    std::fs::create_dir_all(dir).expect("Failed to create png directory");
+    let mut frame_number = 0;
+    simulate_frames(moves, n_knots, rule, bounds, options.steps_per_frame, |rope| {
+        let file = File::create(dir.join(format!("frame_{:05}.png", frame_number)))
+            .expect("Failed to create png file");
+        let mut png = png::Encoder::new(BufWriter::new(file),
+                                        (viewport.width * cell_size) as u32,
+                                        (viewport.height * cell_size) as u32);
+        png.set_color(png::ColorType::Indexed);
+        png.set_depth(png::BitDepth::Eight);
+        png.set_palette(PALETTE.to_vec());
+        png.write_header()
+            .and_then(|mut writer| writer.write_image_data(
+                &frame_to_indexed(&viewport.render(rope), n_knots, cell_size)))
+            .expect("Failed to write png frame");
+        frame_number += 1;
+    });
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use aoc2022_rust::random::Lcg;
     #[test]
     fn it_works() {
-        let input = problem::load_test(09);
-        let solution_1 = solve1(&input);
-        assert_eq!(solution_1, 88);
-        let solution_2 = solve2(&input);
-        assert_eq!(solution_2, 36);
+        let input = problem::load_test(9);
+        let visited = solve(&input, 10, Rc::new(PuzzleRule));
+        assert_eq!(visited[1], 88);
+        assert_eq!(visited[9], 36);
+    }
+
+    #[test]
+    fn can_track_all_knots() {
+        let input = problem::load_test(9);
+        let visited = solve(&input, 10, Rc::new(PuzzleRule));
+        assert_eq!(visited.len(), 10);
+        // A short rope's tail follows the same path as the same knot in a longer rope
+        assert_eq!(solve(&input, 2, Rc::new(PuzzleRule)), visited[..2]);
+        assert_eq!(solve(&input, 5, Rc::new(PuzzleRule)), visited[..5]);
+    }
+
+    fn run_rope(mut rope: Rope, moves: Vec<Move>) -> Vec<usize> {
+        for movement in moves {
+            rope.perform_move(movement);
+        }
+        rope.visited_counts()
+    }
+
+    #[test]
+    fn dense_tracking_matches_sparse() {
+        let input = problem::load_test(9);
+        let bounds = head_bounds(&parse_moves(&input));
+        assert_eq!(bounds, Bounds { min: (-11, -5), max: (14, 15) });
+        assert!(matches!(Visited::for_bounds(bounds, 10), Visited::Dense { .. }));
+
+        let sparse = run_rope(Rope::new(10).track_all_knots(), parse_moves(&input));
+        let dense = run_rope(Rope::with_bounds(10, bounds).track_all_knots(), parse_moves(&input));
+        assert_eq!(sparse, dense);
+    }
+
+    #[test]
+    fn dense_tracking_falls_back_for_large_bounds() {
+        let bounds = Bounds { min: (-100_000, -100_000), max: (100_000, 100_000) };
+        assert!(matches!(Visited::for_bounds(bounds, 1), Visited::Sparse(_)));
+        // Each grid alone would fit but all ten knots' grids together don't
+        let bounds = Bounds { min: (0, 0), max: (8191, 4095) };
+        assert!(matches!(Visited::for_bounds(bounds, 1), Visited::Dense { .. }));
+        assert!(matches!(Visited::for_bounds(bounds, 10), Visited::Sparse(_)));
+
+        // Spiral out with no revisits, the tail cuts one cell off each corner
+        let input = "R 20000\nU 20000\nL 40000\nD 40000";
+        assert!(matches!(Visited::for_bounds(head_bounds(&parse_moves(input)), 2), Visited::Sparse(_)));
+        assert_eq!(solve(input, 2, Rc::new(PuzzleRule)), vec![120_001, 119_997]);
+    }
+
+    #[test]
+    fn can_render_rope() {
+        let input = "R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2";
+        let moves = parse_moves(input);
+        let bounds = head_bounds(&moves);
+        let viewport = Viewport::fit(bounds, 80, 40);
+        assert_eq!((viewport.width, viewport.height, viewport.scale), (6, 5, 1));
+
+        let mut rope = Rope::with_bounds(2, bounds);
+        for movement in moves {
+            rope.perform_move(movement);
+        }
+        assert_eq!(frame_to_string(&viewport.render(&rope)),
+                   "..##..\n...##.\n.1H##.\n....#.\n####..");
+
+        // Squeezing into a 3 x 3 viewport halves the resolution
+        let viewport = Viewport::fit(bounds, 3, 3);
+        assert_eq!((viewport.width, viewport.height, viewport.scale), (3, 3, 2));
+        assert_eq!(frame_to_string(&viewport.render(&rope)), ".##\n1H#\n##.");
+    }
+
+    #[test]
+    fn can_expand_frame_to_palette_indices() {
+        let frame = vec![vec![Pixel::Knot(0), Pixel::Visited], vec![Pixel::Knot(1), Pixel::Empty]];
+        assert_eq!(frame_to_indexed(&frame, 2, 2), vec![
+            3, 3, 1, 1,
+            3, 3, 1, 1,
+            4, 4, 0, 0,
+            4, 4, 0, 0,
+        ]);
+    }
+
+    #[test]
+    fn frames_cover_start_and_end() {
+        let moves = parse_moves("R 4\nU 3");
+        let bounds = head_bounds(&moves);
+        let mut tails = vec![];
+        simulate_frames(moves, 2, &(Rc::new(PuzzleRule) as Rc<dyn FollowRule>), bounds, 4, |rope| tails.push(rope.knot_locations[1]));
+        assert_eq!(tails, vec![(0, 0), (3, 0), (4, 2)]);
+    }
+
+    #[test]
+    fn can_follow_with_other_rules() {
+        assert_eq!(OrthogonalRule.follow((0, 0), (1, 1)), (0, 0));
+        assert_eq!(OrthogonalRule.follow((0, 0), (2, 1)), (1, 0));
+        assert_eq!(OrthogonalRule.follow((0, 0), (1, -2)), (0, -1));
+        assert_eq!(OrthogonalRule.follow((0, 0), (2, 2)), (1, 0));
+
+        let elastic = ElasticRule { slack: 2 };
+        assert_eq!(elastic.follow((0, 0), (2, -2)), (0, 0));
+        assert_eq!(elastic.follow((0, 0), (3, 1)), (1, 1));
+        assert_eq!(elastic.follow((0, 0), (0, -3)), (0, -1));
+
+        // A slack of one is the puzzle rule
+        let input = problem::load_test(9);
+        assert_eq!(solve(&input, 10, Rc::new(ElasticRule { slack: 1 })), solve(&input, 10, Rc::new(PuzzleRule)));
+        // A slacker rope lags further behind the head so its tail visits fewer positions
+        let elastic = solve(&input, 10, Rc::new(ElasticRule { slack: 2 }));
+        assert!(elastic[9] < solve(&input, 10, Rc::new(PuzzleRule))[9]);
+    }
+
+    #[test]
+    fn can_parse_diagonal_moves() {
+        let moves = parse_moves("UR 2\nDR 1\nDL 3\nUL 1");
+        let steps: Vec<(i32, i32)> = moves.iter().map(|movement| movement.step()).collect();
+        assert_eq!(steps, vec![(1, 1), (1, -1), (-1, -1), (-1, 1)]);
+        assert_eq!(head_bounds(&moves), Bounds { min: (-1, -2), max: (3, 2) });
+        // The tail of a two knot rope cuts the corner when the head goes diagonal
+        assert_eq!(solve("UR 3", 2, Rc::new(PuzzleRule)), vec![4, 3]);
+    }
+
+    fn generate_moves(n_moves: usize, max_distance: u64) -> String {
+        let mut random = Lcg::new(2022);
+        let mut lines = vec![];
+        for _ in 0..n_moves {
+            let seed = random.next_u64();
+            let direction = ["R", "L", "U", "D"][(seed >> 62) as usize];
+            let distance = (seed >> 32) % max_distance + 1;
+            lines.push(format!("{} {}", direction, distance));
+        }
+        lines.join("\n")
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_dense_against_sparse() {
+        let input = generate_moves(1_000_000, 20);
+        let bounds = head_bounds(&parse_moves(&input));
+        println!("Head bounds {:?}, {} cells", bounds, bounds.n_cells());
+
+        // Only the tail is tracked, all ten knots' grids over this box wouldn't fit the budget
+        let start = std::time::Instant::now();
+        let sparse = run_rope(Rope::new(10), parse_moves(&input));
+        let sparse_time = start.elapsed();
+
+        let rope = Rope::with_bounds(10, bounds);
+        assert!(matches!(rope.tail_visited(), Visited::Dense { .. }));
+        let start = std::time::Instant::now();
+        let dense = run_rope(rope, parse_moves(&input));
+        let dense_time = start.elapsed();
+
+        println!("Sparse {:?}, dense {:?}", sparse_time, dense_time);
+        assert_eq!(sparse, dense);
     }
 }
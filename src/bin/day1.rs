@@ -1,34 +1,336 @@
 use aoc2022_rust::problem;
+use clap::Parser;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io;
+use std::io::{BufRead, Lines};
+use std::path::PathBuf;
+
+/// Find the elves carrying the most calories
+#[derive(Parser, Debug)]
+struct Cli {
+    /// Number of elves whose calories are summed for puzzle 2
+    #[arg(short, long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    top: u32,
+    /// Read the calories from this file instead of the puzzle input, - for stdin
+    #[arg(short, long)]
+    input: Option<PathBuf>,
+    /// Print statistics about every elf's calories in this format
+    #[arg(short, long, value_enum)]
+    report: Option<ReportFormat>,
+    /// Number of buckets in the report's histogram
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    buckets: u32,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ReportFormat {
+    Text,
+    Json,
+}
 
 fn main() {
-    let input = problem::load(1);
-    let solution = solve(input);
-    println!("Puzzle 1: {}, puzzle 2: {}", solution.0, solution.1);
+    let args = Cli::parse();
+    let k = args.top as usize;
+    let reader = problem::open_input(1, args.input.as_deref());
+    if let Some(format) = args.report {
+        match Elves::new(reader).collect::<io::Result<Vec<Elf>>>() {
+            Ok(elves) => {
+                let report = Report::new(&elves, k, args.buckets as usize);
+                match format {
+                    ReportFormat::Text => println!("{}", report.to_text()),
+                    ReportFormat::Json => println!("{}", report.to_json()),
+                }
+            }
+            Err(error) => eprintln!("{}", error),
+        }
+        return;
+    }
+    match solve(reader, k) {
+        Ok((solution_1, solution_2)) => {
+            println!("Puzzle 1: {}, puzzle 2: {}", solution_1, solution_2);
+        }
+        Err(error) => eprintln!("{}", error),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Elf {
+    // Elves are numbered from 1 in the order they appear
+    index: usize,
+    items: usize,
+    total: u64,
+}
+
+// Reads elves a line at a time. Each elf's items are on consecutive lines and a
+// blank line ends the elf, so any extra blank lines are elves carrying nothing.
+struct Elves<R> {
+    lines: Lines<R>,
+    line_no: usize,
+    index: usize,
+}
+
+impl<R: BufRead> Elves<R> {
+    fn new(reader: R) -> Self {
+        Elves { lines: reader.lines(), line_no: 0, index: 0 }
+    }
+}
+
+impl<R: BufRead> Iterator for Elves<R> {
+    type Item = io::Result<Elf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut elf = Elf { index: self.index + 1, items: 0, total: 0 };
+        let mut blank = false;
+        for line in self.lines.by_ref() {
+            self.line_no += 1;
+            let line = match line {
+                Ok(line) => line,
+                Err(error) => return Some(Err(error)),
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                blank = true;
+                break;
+            }
+            match line.parse::<u64>() {
+                Ok(calories) => {
+                    elf.items += 1;
+                    elf.total += calories;
+                }
+                Err(_) => return Some(Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Invalid calories {:?} on line {}", line, self.line_no)))),
+            }
+        }
+        // Running out of lines only ends an elf that has something
+        if elf.items == 0 && !blank {
+            return None;
+        }
+        self.index += 1;
+        Some(Ok(elf))
+    }
+}
+
+// The highest total and the sum of the k highest
+fn solve(reader: impl BufRead, k: usize) -> io::Result<(u64, u64)> {
+    let highest = top_k(reader, k)?;
+    Ok((highest.first().copied().unwrap_or(0), highest.iter().sum()))
+}
+
+// The k highest elf totals, largest first, read a line at a time so only k totals
+// are held at once
+fn top_k(reader: impl BufRead, k: usize) -> io::Result<Vec<u64>> {
+    // Min heap of the highest so far, so the smallest is the one to drop
+    let mut highest: BinaryHeap<Reverse<u64>> = BinaryHeap::new();
+    for elf in Elves::new(reader) {
+        highest.push(Reverse(elf?.total));
+        if highest.len() > k {
+            highest.pop();
+        }
+    }
+    Ok(highest.into_sorted_vec().into_iter().map(|Reverse(total)| total).collect())
 }
 
-fn solve(input: String) -> (u32, u32) {
-    let mut data = input
-        .split("\n\n")
-        .map(|x| x.lines().map(|x| x.parse::<u32>().unwrap()).sum())
-        .collect::<Vec<u32>>();
-    data.sort_by(|a, b| b.cmp(a));
-    let solution_1 = get_highest_n(&data, 1);
-    let solution_2 = get_highest_n(&data, 3);
-    return (solution_1, solution_2);
+// Elves with totals from and to inclusive
+#[derive(Debug, PartialEq)]
+struct Bucket {
+    from: u64,
+    to: u64,
+    count: usize,
 }
 
-fn get_highest_n(data: &Vec<u32>, n: usize) -> u32 {
-    return data.iter().take(n).sum();
+#[derive(Debug, PartialEq)]
+struct Report {
+    elves: usize,
+    min: u64,
+    median: f64,
+    mean: f64,
+    max: u64,
+    histogram: Vec<Bucket>,
+    // Index and total of the k elves with the most, largest first
+    top: Vec<(usize, u64)>,
+    // Indices of the elves carrying nothing
+    empty: Vec<usize>,
+}
+
+const HISTOGRAM_WIDTH: usize = 40;
+
+impl Report {
+    fn new(elves: &[Elf], k: usize, no_buckets: usize) -> Self {
+        let mut totals: Vec<u64> = elves.iter().map(|elf| elf.total).collect();
+        totals.sort();
+        let (min, max) = (totals.first().copied().unwrap_or(0), totals.last().copied().unwrap_or(0));
+        let median = match totals.len() {
+            0 => 0.0,
+            n if n % 2 == 1 => totals[n / 2] as f64,
+            n => (totals[n / 2 - 1] + totals[n / 2]) as f64 / 2.0,
+        };
+        let mean = if totals.is_empty() { 0.0 } else { totals.iter().sum::<u64>() as f64 / totals.len() as f64 };
+
+        let width = (max - min + 1).div_ceil(no_buckets as u64);
+        let mut histogram: Vec<Bucket> = (0..(max - min + 1).div_ceil(width))
+            // The last bucket stops at the max rather than running on to a full width
+            .map(|bucket| Bucket { from: min + bucket * width, to: (min + (bucket + 1) * width - 1).min(max), count: 0 })
+            .collect();
+        if totals.is_empty() {
+            histogram.clear();
+        }
+        for total in totals.iter() {
+            histogram[((total - min) / width) as usize].count += 1;
+        }
+
+        let mut top: Vec<(usize, u64)> = elves.iter().map(|elf| (elf.index, elf.total)).collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        top.truncate(k);
+        let empty = elves.iter().filter(|elf| elf.items == 0).map(|elf| elf.index).collect();
+        Report { elves: elves.len(), min, median, mean, max, histogram, top, empty }
+    }
+
+    fn to_text(&self) -> String {
+        let mut lines = vec![
+            format!("Elves: {}", self.elves),
+            format!("Min: {}", self.min),
+            format!("Median: {}", self.median),
+            format!("Mean: {:.2}", self.mean),
+            format!("Max: {}", self.max),
+            String::new(),
+        ];
+        let most = self.histogram.iter().map(|bucket| bucket.count).max().unwrap_or(0);
+        let digits = self.max.to_string().len();
+        for bucket in self.histogram.iter() {
+            let bar = if bucket.count == 0 { 0 } else { (bucket.count * HISTOGRAM_WIDTH).div_ceil(most) };
+            lines.push(format!("{:>w$} - {:>w$} | {} {}", bucket.from, bucket.to, "#".repeat(bar), bucket.count, w = digits));
+        }
+        lines.push(String::new());
+        let top: Vec<String> = self.top.iter().map(|(index, total)| format!("elf {} ({})", index, total)).collect();
+        lines.push(format!("Top {}: {}", self.top.len(), top.join(", ")));
+        let empty: Vec<String> = self.empty.iter().map(|index| index.to_string()).collect();
+        lines.push(format!("Empty elves: {}", if empty.is_empty() { String::from("none") } else { empty.join(", ") }));
+        lines.join("\n")
+    }
+
+    fn to_json(&self) -> String {
+        let histogram: Vec<String> = self.histogram.iter()
+            .map(|bucket| format!("{{\"from\":{},\"to\":{},\"count\":{}}}", bucket.from, bucket.to, bucket.count))
+            .collect();
+        let top: Vec<String> = self.top.iter()
+            .map(|(index, total)| format!("{{\"elf\":{},\"calories\":{}}}", index, total))
+            .collect();
+        let empty: Vec<String> = self.empty.iter().map(|index| index.to_string()).collect();
+        format!(
+            "{{\"elves\":{},\"min\":{},\"median\":{},\"mean\":{},\"max\":{},\"histogram\":[{}],\"top\":[{}],\"empty\":[{}]}}",
+            self.elves, self.min, self.median, self.mean, self.max, histogram.join(","), top.join(","), empty.join(","))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use aoc2022_rust::random::Lcg;
+
+    // The original solution, collecting and sorting every total
+    fn solve_sorting(input: String) -> (u32, u32) {
+        let mut data = input
+            .split("\n\n")
+            .map(|x| x.lines().map(|x| x.parse::<u32>().unwrap()).sum())
+            .collect::<Vec<u32>>();
+        data.sort_by(|a, b| b.cmp(a));
+        let solution_1 = get_highest_n(&data, 1);
+        let solution_2 = get_highest_n(&data, 3);
+        (solution_1, solution_2)
+    }
+
+    fn get_highest_n(data: &[u32], n: usize) -> u32 {
+        data.iter().take(n).sum()
+    }
+
     #[test]
     fn it_works() {
         let input = problem::load_test(1);
-        let solution = solve(input);
+        let solution = solve(input.as_bytes(), 3).unwrap();
         assert_eq!(solution.0, 24000);
         assert_eq!(solution.1, 45000);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn streaming_matches_sorting() {
+        let input = problem::load_test(1);
+        assert_eq!(top_k(input.as_bytes(), 3).unwrap(), vec![24000, 11000, 10000]);
+        assert_eq!(top_k(input.as_bytes(), 1).unwrap(), vec![24000]);
+        // Asking for more elves than there are gives them all
+        assert_eq!(top_k(input.as_bytes(), 10).unwrap(), vec![24000, 11000, 10000, 6000, 4000]);
+
+        let mut random = Lcg::new(2022);
+        let mut groups = vec![];
+        for _ in 0..1000 {
+            let seed = random.next_u64();
+            let n_items = (seed >> 60) + 1;
+            let items: Vec<String> = (0..n_items).map(|item| ((seed >> (item * 3)) % 10000).to_string()).collect();
+            groups.push(items.join("\n"));
+        }
+        let input = groups.join("\n\n");
+        let (solution_1, solution_2) = solve_sorting(input.clone());
+        assert_eq!(solve(input.as_bytes(), 3).unwrap(), (solution_1 as u64, solution_2 as u64));
+        let all = top_k(input.as_bytes(), usize::MAX).unwrap();
+        assert_eq!(all.len(), 1000);
+        assert!(all.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn rejects_invalid_calories() {
+        let error = top_k("100\n\n2x0\n".as_bytes(), 3).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid calories \"2x0\" on line 3");
+        // Windows line endings are fine, and a repeated blank line is an elf with nothing
+        assert_eq!(top_k("1\r\n2\r\n\r\n\r\n4\r\n".as_bytes(), 3).unwrap(), vec![4, 3, 0]);
+    }
+
+    #[test]
+    fn can_read_elves() {
+        let elves: Vec<Elf> = Elves::new("1\n2\n\n\n4\n\n".as_bytes()).map(Result::unwrap).collect();
+        assert_eq!(elves, vec![
+            Elf { index: 1, items: 2, total: 3 },
+            Elf { index: 2, items: 0, total: 0 },
+            Elf { index: 3, items: 1, total: 4 }]);
+        assert_eq!(Elves::new("".as_bytes()).count(), 0);
+    }
+
+    #[test]
+    fn can_report_on_elves() {
+        let input = problem::load_raw_test(1);
+        let elves: Vec<Elf> = Elves::new(input.as_bytes()).map(Result::unwrap).collect();
+        let report = Report::new(&elves, 3, 2);
+        assert_eq!(report, Report {
+            elves: 5,
+            min: 4000,
+            median: 10000.0,
+            mean: 11000.0,
+            max: 24000,
+            histogram: vec![Bucket { from: 4000, to: 14000, count: 4 }, Bucket { from: 14001, to: 24000, count: 1 }],
+            top: vec![(4, 24000), (3, 11000), (5, 10000)],
+            empty: vec![],
+        });
+        assert_eq!(report.to_text(), "\
+Elves: 5
+Min: 4000
+Median: 10000
+Mean: 11000.00
+Max: 24000
+
+ 4000 - 14000 | ######################################## 4
+14001 - 24000 | ########## 1
+
+Top 3: elf 4 (24000), elf 3 (11000), elf 5 (10000)
+Empty elves: none");
+        assert_eq!(report.to_json(), "{\"elves\":5,\"min\":4000,\"median\":10000,\"mean\":11000,\"max\":24000,\
+\"histogram\":[{\"from\":4000,\"to\":14000,\"count\":4},{\"from\":14001,\"to\":24000,\"count\":1}],\
+\"top\":[{\"elf\":4,\"calories\":24000},{\"elf\":3,\"calories\":11000},{\"elf\":5,\"calories\":10000}],\"empty\":[]}");
+
+        // Even numbers of elves take the middle two for the median
+        let elves: Vec<Elf> = Elves::new("1\n\n\n4\n\n6\n".as_bytes()).map(Result::unwrap).collect();
+        let report = Report::new(&elves, 1, 10);
+        assert_eq!((report.median, report.empty.clone(), report.top.clone()), (2.5, vec![2], vec![(4, 6)]));
+        assert_eq!(report.histogram.len(), 7);
+        assert!(Report::new(&[], 3, 10).histogram.is_empty());
+    }
+}
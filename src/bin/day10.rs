@@ -1,81 +1,931 @@
-use aoc2022_rust::problem;
+use aoc2022_rust::{ocr, problem};
+use clap::Parser;
 use regex::Regex;
 use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+/// Run the handheld's CPU and draw its CRT
+#[derive(Parser, Debug)]
+struct Cli {
+    /// Width of the CRT in pixels
+    #[arg(long, default_value_t = 40, value_parser = clap::value_parser!(u32).range(1..))]
+    width: u32,
+    /// Height of the CRT in pixels
+    #[arg(long, default_value_t = 6, value_parser = clap::value_parser!(u32).range(1..))]
+    height: u32,
+    /// Width of the sprite in pixels, centred on the X register
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    sprite_width: u32,
+    /// Run this program instead of the puzzle input
+    #[arg(long)]
+    input: Option<PathBuf>,
+    /// Assemble this source file and print the resulting program
+    #[arg(long)]
+    assemble: Option<PathBuf>,
+    /// Print the program annotated with the cycle each instruction starts on and X
+    #[arg(long)]
+    disassemble: bool,
+    /// Step through the program in an interactive debugger
+    #[arg(long)]
+    debug: bool,
+    /// Write the execution trace to this Value Change Dump file
+    #[arg(long)]
+    vcd: Option<PathBuf>,
+}
 
 fn main() {
-    let input = problem::load(10);
+    let args = Cli::parse();
+    if let Some(path) = &args.assemble {
+        let source = fs::read_to_string(path)
+            .unwrap_or_else(|_| panic!("Error reading file {}", path.display()));
+        match assemble(&source) {
+            Ok(program) => println!("{}", listing(&program)),
+            Err(error) => eprintln!("{}", error),
+        }
+        return;
+    }
+    let input = match &args.input {
+        Some(path) => fs::read_to_string(path)
+            .unwrap_or_else(|_| panic!("Error reading file {}", path.display())),
+        None => problem::load(10),
+    };
+    if args.disassemble {
+        println!("{}", disassemble(&parse_program(&input)));
+        return;
+    }
+    let crt = Crt {
+        width: args.width as usize,
+        height: args.height as usize,
+        sprite_width: args.sprite_width as usize,
+    };
+    if args.debug {
+        debug(Debugger::new(parse_program(&input), crt));
+        return;
+    }
+    if let Some(path) = &args.vcd {
+        let mut file = io::BufWriter::new(fs::File::create(path).expect("Failed to create vcd file"));
+        write_vcd(Cpu::new(parse_program(&input)), &crt, &mut file).expect("Failed to write vcd file");
+    }
     let solution_1 = solve1(&input);
     println!("Puzzle 1 {}", solution_1);
-    // let solution_2 = solve2(&input);
-    // println!("Puzzle 2 {}", solution_2);
+    let solution_2 = solve2(&input, &crt);
+    match ocr::parse_str(&solution_2) {
+        Ok(text) => println!("Puzzle 2 {}", text),
+        Err(error) => println!("Puzzle 2\n{}\n{}", solution_2, error),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Instruction {
+    Noop,
+    Addx(i32),
+}
+
+impl Instruction {
+    fn cycles(&self) -> usize {
+        match self {
+            Instruction::Noop => 1,
+            Instruction::Addx(_) => 2,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Instruction::Noop => write!(f, "noop"),
+            Instruction::Addx(value) => write!(f, "addx {}", value),
+        }
+    }
+}
+
+fn parse_instruction(line: &str) -> Instruction {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"^(?:noop|addx (?P<value>-?\d+))$").unwrap();
+    }
+    let capture = RE.captures(line).unwrap_or_else(|| panic!("Invalid instruction {}", line));
+    match capture.name("value") {
+        Some(value) => Instruction::Addx(value.as_str().parse().unwrap()),
+        None => Instruction::Noop,
+    }
+}
+
+fn parse_program(input: &str) -> Vec<Instruction> {
+    input.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(parse_instruction)
+        .collect()
+}
+
+// The assembler takes one statement per line, anything after a '#' is a comment
+//
+//   noop, addx N     emitted as is
+//   set N            addx to make X equal N once it completes
+//   set N at C       X is N from cycle C, padding with noop until the addx must start
+//   until C          pad with noop so the next instruction starts on cycle C
+//   repeat N ... end repeat the enclosed lines N times, blocks can nest
+//   name:            label the cycle the next instruction starts on
+//
+// Cycles C are either a number or a label with an optional offset, e.g. row+39.
+// Labels must be defined before they're used, inside a repeat they're redefined
+// on each pass so refer to the current repetition.
+#[derive(Debug, PartialEq)]
+struct AssembleError {
+    line: usize,
+    message: String,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Error on line {}: {}", self.line, self.message)
+    }
+}
+
+fn assemble_error<T>(line: usize, message: String) -> Result<T, AssembleError> {
+    Err(AssembleError { line, message })
 }
 
-struct Buffer {
-    value: i32
+#[derive(Debug, PartialEq)]
+enum Target {
+    Cycle(usize),
+    Label(String, usize),
 }
 
-fn get_value(input: &str) -> Option<i32> {
+// Statements along with the line they're from
+type Block = Vec<(usize, Statement)>;
+
+#[derive(Debug, PartialEq)]
+enum Statement {
+    Instruction(Instruction),
+    Set(i32, Option<Target>),
+    Until(Target),
+    Label(String),
+    Repeat(usize, Block),
+}
+
+fn parse_number<T: std::str::FromStr>(word: &str, line: usize) -> Result<T, AssembleError> {
+    word.parse().or_else(|_| assemble_error(line, format!("Invalid number {}", word)))
+}
+
+fn parse_label(word: &str, line: usize) -> Result<String, AssembleError> {
     lazy_static! {
-        static ref RE: Regex = Regex::new(r"addx ([-]*\d*)").unwrap();
+        static ref RE: Regex = Regex::new(r"^[A-Za-z_]\w*$").unwrap();
+    }
+    if RE.is_match(word) {
+        Ok(String::from(word))
+    } else {
+        assemble_error(line, format!("Invalid label {}", word))
     }
-    let capture = RE.captures(input);
-    capture?.get(1)?.as_str().parse::<i32>().ok()
+}
+
+fn parse_target(word: &str, line: usize) -> Result<Target, AssembleError> {
+    if word.starts_with(|c: char| c.is_ascii_digit()) {
+        return Ok(Target::Cycle(parse_number(word, line)?));
+    }
+    match word.split_once('+') {
+        Some((label, offset)) => Ok(Target::Label(parse_label(label, line)?, parse_number(offset, line)?)),
+        None => Ok(Target::Label(parse_label(word, line)?, 0)),
+    }
+}
+
+fn parse_source(source: &str) -> Result<Block, AssembleError> {
+    // Stack of open repeat blocks as (line, count, body), the bottom is the whole program
+    let mut blocks: Vec<(usize, usize, Block)> = vec![(0, 1, vec![])];
+    for (index, raw_line) in source.lines().enumerate() {
+        let line = index + 1;
+        let mut code = raw_line.split('#').next().unwrap().trim();
+        if let Some((label, rest)) = code.split_once(':') {
+            let label = parse_label(label.trim(), line)?;
+            blocks.last_mut().unwrap().2.push((line, Statement::Label(label)));
+            code = rest.trim();
+        }
+        let statement = match code.split_whitespace().collect::<Vec<&str>>().as_slice() {
+            [] => continue,
+            ["noop"] => Statement::Instruction(Instruction::Noop),
+            ["addx", value] => Statement::Instruction(Instruction::Addx(parse_number(value, line)?)),
+            ["set", value] => Statement::Set(parse_number(value, line)?, None),
+            ["set", value, "at", target] => {
+                Statement::Set(parse_number(value, line)?, Some(parse_target(target, line)?))
+            }
+            ["until", target] => Statement::Until(parse_target(target, line)?),
+            ["repeat", count] => {
+                blocks.push((line, parse_number(count, line)?, vec![]));
+                continue;
+            }
+            ["end"] => {
+                if blocks.len() == 1 {
+                    return assemble_error(line, String::from("end without a repeat"));
+                }
+                let (start, count, body) = blocks.pop().unwrap();
+                blocks.last_mut().unwrap().2.push((start, Statement::Repeat(count, body)));
+                continue;
+            }
+            _ => return assemble_error(line, format!("Unknown statement {}", code)),
+        };
+        blocks.last_mut().unwrap().2.push((line, statement));
+    }
+    if blocks.len() > 1 {
+        return assemble_error(blocks.last().unwrap().0, String::from("repeat without an end"));
+    }
+    Ok(blocks.pop().unwrap().2)
+}
+
+struct Assembler {
+    program: Vec<Instruction>,
+    // The cycle the next instruction will start on and the value of X by then
+    cycle: usize,
+    x: i32,
+    labels: HashMap<String, usize>,
+}
+
+impl Assembler {
+    fn emit(&mut self, instruction: Instruction) {
+        self.cycle += instruction.cycles();
+        if let Instruction::Addx(value) = instruction {
+            self.x += value;
+        }
+        self.program.push(instruction);
+    }
+
+    fn resolve(&self, target: &Target, line: usize) -> Result<usize, AssembleError> {
+        match target {
+            Target::Cycle(cycle) => Ok(*cycle),
+            Target::Label(label, offset) => match self.labels.get(label) {
+                Some(cycle) => Ok(cycle + offset),
+                None => assemble_error(line, format!("Unknown label {}", label)),
+            },
+        }
+    }
+
+    fn pad_until(&mut self, cycle: usize, line: usize) -> Result<(), AssembleError> {
+        if self.cycle > cycle {
+            return assemble_error(line, format!(
+                "Cycle {} has already passed, the next instruction starts on cycle {}",
+                cycle, self.cycle));
+        }
+        while self.cycle < cycle {
+            self.emit(Instruction::Noop);
+        }
+        Ok(())
+    }
+
+    fn assemble_block(&mut self, statements: &[(usize, Statement)]) -> Result<(), AssembleError> {
+        for (line, statement) in statements {
+            match statement {
+                Statement::Instruction(instruction) => self.emit(*instruction),
+                Statement::Set(value, target) => {
+                    if let Some(target) = target {
+                        // addx takes two cycles and X only changes once it finishes
+                        let cycle = self.resolve(target, *line)?;
+                        if cycle < 3 {
+                            return assemble_error(*line, format!("X can't change before cycle 3, not {}", cycle));
+                        }
+                        self.pad_until(cycle - 2, *line)?;
+                    }
+                    match value.checked_sub(self.x) {
+                        Some(change) => self.emit(Instruction::Addx(change)),
+                        None => return assemble_error(*line, format!("Can't change X from {} to {} with one addx", self.x, value)),
+                    }
+                }
+                Statement::Until(target) => {
+                    let cycle = self.resolve(target, *line)?;
+                    self.pad_until(cycle, *line)?;
+                }
+                Statement::Label(label) => {
+                    self.labels.insert(label.clone(), self.cycle);
+                }
+                Statement::Repeat(count, body) => {
+                    for _ in 0..*count {
+                        self.assemble_block(body)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn assemble(source: &str) -> Result<Vec<Instruction>, AssembleError> {
+    let statements = parse_source(source)?;
+    let mut assembler = Assembler {
+        program: vec![],
+        cycle: 1,
+        x: 1,
+        labels: HashMap::new(),
+    };
+    assembler.assemble_block(&statements)?;
+    Ok(assembler.program)
+}
+
+fn listing(program: &[Instruction]) -> String {
+    program.iter()
+        .map(|instruction| instruction.to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// Annotates each instruction with the cycle it starts on and the value of X while
+// it runs, the output is still valid assembler source
+fn disassemble(program: &[Instruction]) -> String {
+    Cpu::new(program.to_vec())
+        .filter(|cycle| cycle.number == cycle.start)
+        .map(|cycle| {
+            let after = match cycle.instruction {
+                Instruction::Addx(value) => format!(" -> {}", cycle.x + value),
+                Instruction::Noop => String::new(),
+            };
+            format!("{:<10} # cycle {}, X {}{}", cycle.instruction.to_string(), cycle.number, cycle.x, after)
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// The state of the CPU during a single clock cycle
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cycle {
+    // Cycles are numbered from 1 as in the puzzle
+    number: usize,
+    // Value of the X register during the cycle, updates from addx only land after it
+    x: i32,
+    // Index into the program of the instruction executing in this cycle
+    line: usize,
+    instruction: Instruction,
+    // The cycle the instruction started on
+    start: usize,
+}
+
+struct Cpu {
+    program: Vec<Instruction>,
+    x: i32,
+    cycle: usize,
+    // Index of the executing instruction and how many cycles it has already taken
+    line: usize,
+    instruction_cycle: usize,
+}
+
+impl Cpu {
+    fn new(program: Vec<Instruction>) -> Self {
+        Cpu {
+            program,
+            x: 1,
+            cycle: 0,
+            line: 0,
+            instruction_cycle: 0,
+        }
+    }
+
+    fn is_halted(&self) -> bool {
+        self.line >= self.program.len()
+    }
+
+    // Runs the program to the end, calling on_cycle during every cycle
+    fn run(&mut self, mut on_cycle: impl FnMut(&Cycle)) {
+        for cycle in self.by_ref() {
+            on_cycle(&cycle);
+        }
+    }
+}
+
+impl Iterator for Cpu {
+    type Item = Cycle;
+
+    // Executes one clock cycle, returning the state seen during it
+    fn next(&mut self) -> Option<Cycle> {
+        if self.is_halted() {
+            return None;
+        }
+        let instruction = self.program[self.line];
+        self.cycle += 1;
+        let during = Cycle {
+            number: self.cycle,
+            x: self.x,
+            line: self.line,
+            instruction,
+            start: self.cycle - self.instruction_cycle,
+        };
+        self.instruction_cycle += 1;
+        if self.instruction_cycle == instruction.cycles() {
+            if let Instruction::Addx(value) = instruction {
+                self.x += value;
+            }
+            self.line += 1;
+            self.instruction_cycle = 0;
+        }
+        Some(during)
+    }
+}
+
+fn signal_strength(cycle: &Cycle) -> i32 {
+    cycle.number as i32 * cycle.x
 }
 
 fn solve1(input: &str) -> i32 {
-    let cumulative_sum: Vec<i32> = input
-        .lines()
-        .map(|x| get_value(x.trim()))
-        .scan(0, |acc, x| {
-            match x {
-                Some(v) => {
-                    *acc = (*acc) + v;
-                    println!("{}, {}", v, *acc);
-                    Some((*acc))
-                },
-                None => {
-                    println!("{}", *acc);
-                    Some(*acc)
+    let mut sum = 0;
+    Cpu::new(parse_program(input)).run(|cycle| {
+        // Interesting cycles are 20, 60, 100, 140, 180, 220
+        if cycle.number <= 220 && cycle.number % 40 == 20 {
+            sum += signal_strength(cycle);
+        }
+    });
+    sum
+}
+
+struct Crt {
+    width: usize,
+    height: usize,
+    sprite_width: usize,
+}
+
+impl Default for Crt {
+    fn default() -> Self {
+        Crt {
+            width: 40,
+            height: 6,
+            sprite_width: 3,
+        }
+    }
+}
+
+impl Crt {
+    // The (column, row) the beam draws during a cycle
+    fn beam_position(&self, cycle: &Cycle) -> (usize, usize) {
+        let pixel = (cycle.number - 1) % (self.width * self.height);
+        (pixel % self.width, pixel / self.width)
+    }
+
+    // The sprite is sprite_width pixels wide with X as its middle pixel
+    fn is_lit(&self, cycle: &Cycle) -> bool {
+        let (column, _) = self.beam_position(cycle);
+        let start = cycle.x - (self.sprite_width as i32 - 1) / 2;
+        (start..start + self.sprite_width as i32).contains(&(column as i32))
+    }
+
+    fn blank(&self, pixel: char) -> Vec<Vec<char>> {
+        vec![vec![pixel; self.width]; self.height]
+    }
+
+    // Sets the pixel under the beam to '#' if lit and '.' otherwise
+    fn plot(&self, pixels: &mut [Vec<char>], cycle: &Cycle) {
+        let (column, row) = self.beam_position(cycle);
+        pixels[row][column] = if self.is_lit(cycle) { '#' } else { '.' };
+    }
+
+    // Draws one frame, anything after the first width x height cycles is ignored
+    fn draw(&self, cycles: impl Iterator<Item=Cycle>) -> String {
+        let mut pixels = self.blank('.');
+        for cycle in cycles.take(self.width * self.height) {
+            self.plot(&mut pixels, &cycle);
+        }
+        pixels_to_string(&pixels)
+    }
+}
+
+fn pixels_to_string(pixels: &[Vec<char>]) -> String {
+    pixels.iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn solve2(input: &str, crt: &Crt) -> String {
+    crt.draw(Cpu::new(parse_program(input)))
+}
+
+
+// Expressions for conditional breakpoints and watches over the registers x, cycle
+// and line, supporting integers, + - *, comparisons, && and || and brackets.
+// Comparisons and logic give 1 for true and 0 for false.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+}
+
+#[derive(Debug, PartialEq)]
+enum Expression {
+    Number(i64),
+    X,
+    Cycle,
+    Line,
+    Negate(Box<Expression>),
+    Binary(Box<Expression>, Operator, Box<Expression>),
+}
+
+impl Expression {
+    fn evaluate(&self, cycle: &Cycle) -> i64 {
+        match self {
+            Expression::Number(value) => *value,
+            Expression::X => cycle.x as i64,
+            Expression::Cycle => cycle.number as i64,
+            Expression::Line => cycle.line as i64 + 1,
+            // Wraps rather than overflowing so a watch can't end the session
+            Expression::Negate(expression) => expression.evaluate(cycle).wrapping_neg(),
+            Expression::Binary(left, operator, right) => {
+                let (left, right) = (left.evaluate(cycle), right.evaluate(cycle));
+                match operator {
+                    Operator::Add => left.wrapping_add(right),
+                    Operator::Subtract => left.wrapping_sub(right),
+                    Operator::Multiply => left.wrapping_mul(right),
+                    Operator::Equal => (left == right) as i64,
+                    Operator::NotEqual => (left != right) as i64,
+                    Operator::Less => (left < right) as i64,
+                    Operator::LessEqual => (left <= right) as i64,
+                    Operator::Greater => (left > right) as i64,
+                    Operator::GreaterEqual => (left >= right) as i64,
+                    Operator::And => (left != 0 && right != 0) as i64,
+                    Operator::Or => (left != 0 || right != 0) as i64,
                 }
             }
-        }).collect();
+        }
+    }
+}
 
+fn tokenise(text: &str) -> Result<Vec<&str>, String> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"\d+|[A-Za-z_]\w*|==|!=|<=|>=|&&|\|\||[-+*<>()]").unwrap();
+    }
+    let mut tokens = vec![];
+    let mut end = 0;
+    for token in RE.find_iter(text) {
+        if !text[end..token.start()].trim().is_empty() {
+            return Err(format!("Unexpected {}", text[end..token.start()].trim()));
+        }
+        tokens.push(token.as_str());
+        end = token.end();
+    }
+    if !text[end..].trim().is_empty() {
+        return Err(format!("Unexpected {}", text[end..].trim()));
+    }
+    Ok(tokens)
+}
+
+// Precedence climbing parser, each level lists its operators from loosest to tightest
+const PRECEDENCE: [&[(&str, Operator)]; 4] = [
+    &[("||", Operator::Or)],
+    &[("&&", Operator::And)],
+    &[("==", Operator::Equal), ("!=", Operator::NotEqual), ("<", Operator::Less),
+        ("<=", Operator::LessEqual), (">", Operator::Greater), (">=", Operator::GreaterEqual)],
+    &[("+", Operator::Add), ("-", Operator::Subtract)],
+];
 
-    calc_signal_strength(&cumulative_sum, 20) +
-        calc_signal_strength(&cumulative_sum, 60) +
-        calc_signal_strength(&cumulative_sum, 100) +
-        calc_signal_strength(&cumulative_sum, 140) +
-        calc_signal_strength(&cumulative_sum, 180) +
-        calc_signal_strength(&cumulative_sum, 220)
+struct ExpressionParser<'a> {
+    tokens: Vec<&'a str>,
+    position: usize,
 }
 
-fn calc_signal_strength(sums: &Vec<i32>, step: i32) -> i32 {
-    let mut sum: i32 = 0;
-    let mut cycle = 0;
-    for pair in sums.windows(2) {
-        cycle += 1;
-        if pair[0] == pair[1] {
-            continue;
+impl<'a> ExpressionParser<'a> {
+    fn next(&mut self) -> Option<&'a str> {
+        let token = self.tokens.get(self.position).copied();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expression, String> {
+        if level == PRECEDENCE.len() {
+            return self.product();
         }
-        // check if cycle in the set of 20, 40, ... then add em
-        sum += pair[0];
+        let mut left = self.binary(level + 1)?;
+        while let Some(&(_, operator)) = PRECEDENCE[level].iter().find(|(token, _)| Some(*token) == self.peek()) {
+            self.next();
+            left = Expression::Binary(Box::new(left), operator, Box::new(self.binary(level + 1)?));
+        }
+        Ok(left)
     }
-    let value = if step == sums.len() as i32 {
-        sums[(step - 1) as usize]
-    } else {
-        sums[(step) as usize]
-    };
-    step * value
+
+    fn product(&mut self) -> Result<Expression, String> {
+        let mut left = self.unary()?;
+        while self.peek() == Some("*") {
+            self.next();
+            left = Expression::Binary(Box::new(left), Operator::Multiply, Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expression, String> {
+        match self.next() {
+            Some("-") => Ok(Expression::Negate(Box::new(self.unary()?))),
+            Some("(") => {
+                let expression = self.binary(0)?;
+                match self.next() {
+                    Some(")") => Ok(expression),
+                    _ => Err(String::from("Missing )")),
+                }
+            }
+            Some("x") | Some("X") => Ok(Expression::X),
+            Some("cycle") => Ok(Expression::Cycle),
+            Some("line") => Ok(Expression::Line),
+            Some(token) => token.parse()
+                .map(Expression::Number)
+                .map_err(|_| format!("Unexpected {}", token)),
+            None => Err(String::from("Unexpected end of expression")),
+        }
+    }
+}
+
+fn parse_expression(text: &str) -> Result<Expression, String> {
+    let mut parser = ExpressionParser { tokens: tokenise(text)?, position: 0 };
+    let expression = parser.binary(0)?;
+    match parser.peek() {
+        None => Ok(expression),
+        Some(token) => Err(format!("Unexpected {}", token)),
+    }
+}
+
+#[derive(Debug)]
+enum Breakpoint {
+    Cycle(usize),
+    // Instruction lines count from 1 like the lines of the puzzle input
+    Line(usize),
+    Condition(String, Expression),
 }
 
-// fn solve2(input: &str) -> u32 {
-//     return input
-//         .lines()
-//         .map(|x| new_pair(x.trim()).range_overlaps())
-//         .filter(|x| *x)
-//         .count() as u32;
-// }
+impl Breakpoint {
+    fn is_hit(&self, cycle: &Cycle) -> bool {
+        match self {
+            Breakpoint::Cycle(number) => cycle.number == *number,
+            // Only break on the first cycle of the instruction
+            Breakpoint::Line(line) => cycle.line + 1 == *line && cycle.number == cycle.start,
+            Breakpoint::Condition(_, expression) => expression.evaluate(cycle) != 0,
+        }
+    }
+}
 
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Breakpoint::Cycle(number) => write!(f, "cycle {}", number),
+            Breakpoint::Line(line) => write!(f, "line {}", line),
+            Breakpoint::Condition(text, _) => write!(f, "if {}", text),
+        }
+    }
+}
+
+const DEBUGGER_HELP: &str = "\
+step [n]            run n cycles, default 1
+next [n]            run until n more instructions have started, default 1
+continue            run until a breakpoint or the end of the program
+break cycle <n>     stop during cycle n
+break line <n>      stop when the instruction on line n starts
+break if <expr>     stop during any cycle where expr is non zero
+watch <expr>        show expr whenever the program stops
+delete <n>          remove breakpoint n
+unwatch <n>         remove watch n
+info                list breakpoints and watches
+print               show the registers and watches
+crt                 show the CRT drawn so far
+quit                leave the debugger
+Expressions use x, cycle, line, integers, + - *, comparisons, && and ||";
+
+struct Debugger {
+    cpu: Cpu,
+    crt: Crt,
+    // The cycle the program stopped during, None before the first cycle
+    current: Option<Cycle>,
+    // Pixels drawn so far, undrawn pixels are blank
+    pixels: Vec<Vec<char>>,
+    breakpoints: Vec<Breakpoint>,
+    watches: Vec<(String, Expression)>,
+}
+
+impl Debugger {
+    fn new(program: Vec<Instruction>, crt: Crt) -> Self {
+        let pixels = crt.blank(' ');
+        Debugger {
+            cpu: Cpu::new(program),
+            crt,
+            current: None,
+            pixels,
+            breakpoints: vec![],
+            watches: vec![],
+        }
+    }
+
+    // Runs cycles until stop returns true, a breakpoint is hit or the program ends
+    fn run_until(&mut self, mut stop: impl FnMut(&Cycle) -> bool) -> String {
+        loop {
+            let cycle = match self.cpu.next() {
+                Some(cycle) => cycle,
+                None => return format!("Program finished after {} cycles with X {}\n{}",
+                                       self.cpu.cycle, self.cpu.x, self.status()),
+            };
+            self.crt.plot(&mut self.pixels, &cycle);
+            self.current = Some(cycle);
+            if let Some(index) = self.breakpoints.iter().position(|breakpoint| breakpoint.is_hit(&cycle)) {
+                return format!("Breakpoint {}, {}\n{}", index + 1, self.breakpoints[index], self.status());
+            }
+            if stop(&cycle) {
+                return self.status();
+            }
+        }
+    }
+
+    fn status(&self) -> String {
+        let mut lines = vec![match &self.current {
+            Some(cycle) => format!("cycle {} X {} line {} {}",
+                                   cycle.number, cycle.x, cycle.line + 1, cycle.instruction),
+            None => format!("Not started, X {}", self.cpu.x),
+        }];
+        if let Some(cycle) = &self.current {
+            for (text, expression) in self.watches.iter() {
+                lines.push(format!("{} = {}", text, expression.evaluate(cycle)));
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn info(&self) -> String {
+        let mut lines = vec![];
+        for (index, breakpoint) in self.breakpoints.iter().enumerate() {
+            lines.push(format!("Breakpoint {}: {}", index + 1, breakpoint));
+        }
+        for (index, (text, _)) in self.watches.iter().enumerate() {
+            lines.push(format!("Watch {}: {}", index + 1, text));
+        }
+        if lines.is_empty() {
+            lines.push(String::from("No breakpoints or watches"));
+        }
+        lines.join("\n")
+    }
+
+    fn parse_count(argument: &str) -> Result<usize, String> {
+        if argument.is_empty() {
+            return Ok(1);
+        }
+        match argument.parse() {
+            Ok(count) if count > 0 => Ok(count),
+            _ => Err(format!("Invalid count {}", argument)),
+        }
+    }
+
+    fn parse_index(argument: &str, len: usize) -> Result<usize, String> {
+        match argument.parse::<usize>() {
+            Ok(index) if index >= 1 && index <= len => Ok(index - 1),
+            _ => Err(format!("No number {}", argument)),
+        }
+    }
+
+    // Runs one command and returns what to show the user
+    fn execute(&mut self, command: &str) -> Result<String, String> {
+        let (name, argument) = command.trim().split_once(' ').unwrap_or((command.trim(), ""));
+        let argument = argument.trim();
+        match name {
+            "s" | "step" => {
+                let mut remaining = Debugger::parse_count(argument)?;
+                Ok(self.run_until(|_| {
+                    remaining -= 1;
+                    remaining == 0
+                }))
+            }
+            "n" | "next" => {
+                let mut remaining = Debugger::parse_count(argument)?;
+                Ok(self.run_until(|cycle| {
+                    if cycle.number == cycle.start {
+                        remaining -= 1;
+                    }
+                    remaining == 0
+                }))
+            }
+            "c" | "continue" => Ok(self.run_until(|_| false)),
+            "b" | "break" => {
+                let breakpoint = match argument.split_once(' ') {
+                    Some(("cycle", number)) => Breakpoint::Cycle(number.trim().parse()
+                        .map_err(|_| format!("Invalid cycle {}", number))?),
+                    Some(("line", line)) => Breakpoint::Line(line.trim().parse()
+                        .map_err(|_| format!("Invalid line {}", line))?),
+                    Some(("if", condition)) => {
+                        Breakpoint::Condition(String::from(condition.trim()), parse_expression(condition)?)
+                    }
+                    _ => return Err(String::from("Expected break cycle <n>, break line <n> or break if <expr>")),
+                };
+                self.breakpoints.push(breakpoint);
+                Ok(format!("Breakpoint {}: {}", self.breakpoints.len(), self.breakpoints.last().unwrap()))
+            }
+            "w" | "watch" => {
+                let expression = parse_expression(argument)?;
+                self.watches.push((String::from(argument), expression));
+                Ok(format!("Watch {}: {}", self.watches.len(), argument))
+            }
+            "d" | "delete" => {
+                let index = Debugger::parse_index(argument, self.breakpoints.len())?;
+                Ok(format!("Deleted breakpoint {}", self.breakpoints.remove(index)))
+            }
+            "unwatch" => {
+                let index = Debugger::parse_index(argument, self.watches.len())?;
+                Ok(format!("Deleted watch {}", self.watches.remove(index).0))
+            }
+            "i" | "info" => Ok(self.info()),
+            "p" | "print" => Ok(self.status()),
+            "crt" => Ok(pixels_to_string(&self.pixels)),
+            "h" | "help" => Ok(String::from(DEBUGGER_HELP)),
+            _ => Err(format!("Unknown command {}, try help", name)),
+        }
+    }
+}
+
+fn debug(mut debugger: Debugger) {
+    println!("{}", debugger.status());
+    let mut stdout = io::stdout();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(dbg) ");
+        stdout.flush().expect("Failed to flush prompt");
+        let command = match lines.next() {
+            Some(line) => line.expect("Failed to read command"),
+            None => break,
+        };
+        match command.trim() {
+            "" => continue,
+            "q" | "quit" => break,
+            command => match debugger.execute(command) {
+                Ok(output) => println!("{}", output),
+                Err(error) => println!("{}", error),
+            },
+        }
+    }
+}
+
+// Signals written to the VCD file as (identifier, type, width, name)
+const VCD_SIGNALS: [(char, &str, usize, &str); 7] = [
+    ('!', "wire", 1, "clk"),
+    ('"', "integer", 32, "cycle"),
+    ('#', "integer", 32, "x"),
+    ('$', "integer", 32, "line"),
+    ('%', "string", 1, "instruction"),
+    ('&', "integer", 32, "beam_x"),
+    ('\'', "integer", 32, "beam_y"),
+];
+
+// VCD values can't contain spaces so the operand is joined with an underscore
+fn vcd_instruction(instruction: &Instruction) -> String {
+    instruction.to_string().replace(' ', "_")
+}
+
+// Writes the trace as a Value Change Dump. Each cycle lasts two time units with
+// the clock high for the first, and every other signal changes as the clock rises
+// to the value it holds during that cycle. Lines count from 1.
+fn write_vcd(cpu: Cpu, crt: &Crt, out: &mut impl Write) -> io::Result<()> {
+    writeln!(out, "$version aoc2022-rust day 10 $end")?;
+    writeln!(out, "$timescale 1ns $end")?;
+    writeln!(out, "$scope module handheld $end")?;
+    for (id, kind, width, name) in VCD_SIGNALS.iter() {
+        writeln!(out, "$var {} {} {} {} $end", kind, width, id, name)?;
+    }
+    writeln!(out, "$upscope $end")?;
+    writeln!(out, "$enddefinitions $end")?;
+
+    let mut previous: Vec<Option<String>> = vec![None; VCD_SIGNALS.len()];
+    let mut time = 0;
+    for cycle in cpu {
+        let (beam_x, beam_y) = crt.beam_position(&cycle);
+        let values = [
+            String::from("1"),
+            format!("b{:b}", cycle.number),
+            // Negative values are written in two's complement
+            format!("b{:b}", cycle.x as u32),
+            format!("b{:b}", cycle.line + 1),
+            format!("s{}", vcd_instruction(&cycle.instruction)),
+            format!("b{:b}", beam_x),
+            format!("b{:b}", beam_y),
+        ];
+        writeln!(out, "#{}", time)?;
+        for ((value, last), (id, _, width, _)) in values.into_iter().zip(previous.iter_mut()).zip(VCD_SIGNALS.iter()) {
+            if last.as_ref() != Some(&value) {
+                // Single bit values are written without a space before the identifier
+                if *width == 1 && !value.starts_with('s') {
+                    writeln!(out, "{}{}", value, id)?;
+                } else {
+                    writeln!(out, "{} {}", value, id)?;
+                }
+                *last = Some(value);
+            }
+        }
+        writeln!(out, "#{}", time + 1)?;
+        writeln!(out, "0!")?;
+        previous[0] = Some(String::from("0"));
+        time += 2;
+    }
+    writeln!(out, "#{}", time)?;
+    Ok(())
+}
 
 #[cfg(test)]
 mod tests {
@@ -85,7 +935,232 @@ mod tests {
         let input = problem::load_test(10);
         let solution_1 = solve1(&input);
         assert_eq!(solution_1, 13140);
-        // let solution_2 = solve2(&input);
-        // assert_eq!(solution_2, 4);
+        let solution_2 = solve2(&input, &Crt::default());
+        assert_eq!(solution_2, "\
+##..##..##..##..##..##..##..##..##..##..
+###...###...###...###...###...###...###.
+####....####....####....####....####....
+#####.....#####.....#####.....#####.....
+######......######......######......####
+#######.......#######.......#######.....");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn can_configure_crt() {
+        let program = parse_program("addx 2\naddx 2\nnoop\nnoop\nnoop");
+        let crt = Crt { width: 4, height: 2, sprite_width: 1 };
+        // X is 1, 1, 3, 3, 5, 5, 5 so a single pixel sprite meets the beam at columns 1 and 3
+        assert_eq!(crt.draw(Cpu::new(program)), ".#.#\n....");
+        // An even width sprite covers one pixel left of X and two to the right
+        let crt = Crt { width: 8, height: 1, sprite_width: 4 };
+        assert_eq!(crt.draw(Cpu::new(vec![Instruction::Noop; 8])), "####....");
+    }
+
+    #[test]
+    fn can_trace_x_register() {
+        let program = parse_program("noop\naddx 3\naddx -5");
+        assert_eq!(program, vec![Instruction::Noop, Instruction::Addx(3), Instruction::Addx(-5)]);
+        let mut cpu = Cpu::new(program);
+        let trace: Vec<(usize, i32, usize)> = cpu.by_ref()
+            .map(|cycle| (cycle.number, cycle.x, cycle.line))
+            .collect();
+        assert_eq!(trace, vec![(1, 1, 0), (2, 1, 1), (3, 1, 1), (4, 4, 2), (5, 4, 2)]);
+        assert!(cpu.is_halted());
+        assert_eq!(cpu.x, -1);
+    }
+
+    #[test]
+    fn can_observe_signal_strength() {
+        let input = problem::load_test(10);
+        let mut strengths = vec![];
+        Cpu::new(parse_program(&input)).run(|cycle| {
+            if cycle.number % 40 == 20 {
+                strengths.push(signal_strength(cycle));
+            }
+        });
+        assert_eq!(strengths, vec![420, 1140, 1800, 2940, 2880, 3960]);
+    }
+
+    fn x_during(program: &[Instruction], cycle: usize) -> i32 {
+        Cpu::new(program.to_vec()).nth(cycle - 1).unwrap().x
+    }
+
+    #[test]
+    fn can_assemble_set_at_cycle() {
+        let program = assemble("set 5 at 20 # comment\nset -3").unwrap();
+        assert_eq!(program.len(), 19);
+        assert_eq!(program[17], Instruction::Addx(4));
+        assert_eq!(program[18], Instruction::Addx(-8));
+        assert_eq!(x_during(&program, 19), 1);
+        assert_eq!(x_during(&program, 20), 5);
+        let mut cpu = Cpu::new(program);
+        cpu.run(|_| ());
+        assert_eq!(cpu.x, -3);
+    }
+
+    #[test]
+    fn can_assemble_repeats_and_labels() {
+        let source = "\
+# Move the sprite along by 10 at the start of each row
+repeat 3
+  row: set 10 at row+11
+  addx 10
+  until row+40
+end
+";
+        let program = assemble(source).unwrap();
+        assert_eq!(program.iter().map(|i| i.cycles()).sum::<usize>(), 120);
+        assert_eq!(x_during(&program, 12), 10);
+        assert_eq!(x_during(&program, 14), 20);
+        assert_eq!(x_during(&program, 52), 10);
+        assert_eq!(x_during(&program, 54), 20);
+        assert_eq!(x_during(&program, 120), 20);
+    }
+
+    #[test]
+    fn reports_assembly_errors() {
+        assert_eq!(assemble("noop\njump 3"),
+                   Err(AssembleError { line: 2, message: String::from("Unknown statement jump 3") }));
+        assert_eq!(assemble("repeat 2\nnoop"),
+                   Err(AssembleError { line: 1, message: String::from("repeat without an end") }));
+        assert_eq!(assemble("end"),
+                   Err(AssembleError { line: 1, message: String::from("end without a repeat") }));
+        assert_eq!(assemble("set 3 at later"),
+                   Err(AssembleError { line: 1, message: String::from("Unknown label later") }));
+        assert_eq!(assemble("until 5\nset 2 at 5").unwrap_err().to_string(),
+                   "Error on line 2: Cycle 3 has already passed, the next instruction starts on cycle 5");
+        assert_eq!(assemble("noop\nset -2147483648"),
+                   Err(AssembleError { line: 2, message: String::from("Can't change X from 1 to -2147483648 with one addx") }));
+    }
+
+    #[test]
+    fn disassembly_round_trips() {
+        let program = parse_program("noop\naddx 3\naddx -5");
+        let disassembled = disassemble(&program);
+        assert_eq!(disassembled, "\
+noop       # cycle 1, X 1
+addx 3     # cycle 2, X 1 -> 4
+addx -5    # cycle 4, X 4 -> -1");
+        assert_eq!(assemble(&disassembled), Ok(program));
+
+        let program = parse_program(&problem::load_test(10));
+        assert_eq!(assemble(&disassemble(&program)), Ok(program.clone()));
+        assert_eq!(parse_program(&listing(&program)), program);
+    }
+
+    #[test]
+    fn can_parse_expressions() {
+        let cycle = Cycle { number: 20, x: 21, line: 10, instruction: Instruction::Noop, start: 20 };
+        let evaluate = |text: &str| parse_expression(text).unwrap().evaluate(&cycle);
+        assert_eq!(evaluate("x * cycle"), 420);
+        assert_eq!(evaluate("1 + 2 * 3 - -4"), 11);
+        assert_eq!(evaluate("(1 + 2) * 3"), 9);
+        assert_eq!(evaluate("x > 20 && line == 11"), 1);
+        assert_eq!(evaluate("x < 0 || cycle >= 21"), 0);
+        assert_eq!(evaluate("9999999999 * 9999999999"), 9999999999i64.wrapping_mul(9999999999));
+        assert_eq!(evaluate("-(0 - 9223372036854775807 - 1)"), i64::MIN);
+        assert_eq!(parse_expression("x +"), Err(String::from("Unexpected end of expression")));
+        assert_eq!(parse_expression("x ? 1"), Err(String::from("Unexpected ?")));
+        assert_eq!(parse_expression("(x"), Err(String::from("Missing )")));
+        assert_eq!(parse_expression("x 1"), Err(String::from("Unexpected 1")));
+    }
+
+    #[test]
+    fn can_step_through_program() {
+        let mut debugger = Debugger::new(parse_program("noop\naddx 3\naddx -5"), Crt::default());
+        assert_eq!(debugger.execute("print"), Ok(String::from("Not started, X 1")));
+        assert_eq!(debugger.execute("step"), Ok(String::from("cycle 1 X 1 line 1 noop")));
+        assert_eq!(debugger.execute("next"), Ok(String::from("cycle 2 X 1 line 2 addx 3")));
+        assert_eq!(debugger.execute("next"), Ok(String::from("cycle 4 X 4 line 3 addx -5")));
+        assert_eq!(debugger.execute("watch x * 2"), Ok(String::from("Watch 1: x * 2")));
+        assert_eq!(debugger.execute("step 5"),
+                   Ok(String::from("Program finished after 5 cycles with X -1\ncycle 5 X 4 line 3 addx -5\nx * 2 = 8")));
+        assert_eq!(debugger.execute("crt").unwrap().lines().next().map(|row| row.trim_end()), Some("#####"));
+        assert_eq!(debugger.execute("jump"), Err(String::from("Unknown command jump, try help")));
+        assert_eq!(debugger.execute("step 0"), Err(String::from("Invalid count 0")));
+        assert_eq!(debugger.execute("next 0"), Err(String::from("Invalid count 0")));
+    }
+
+    #[test]
+    fn can_stop_at_breakpoints() {
+        let input = problem::load_test(10);
+        let mut debugger = Debugger::new(parse_program(&input), Crt::default());
+        assert_eq!(debugger.execute("break cycle 20"), Ok(String::from("Breakpoint 1: cycle 20")));
+        assert_eq!(debugger.execute("break line 30"), Ok(String::from("Breakpoint 2: line 30")));
+        assert_eq!(debugger.execute("watch x * cycle"), Ok(String::from("Watch 1: x * cycle")));
+        assert_eq!(debugger.execute("continue"),
+                   Ok(String::from("Breakpoint 1, cycle 20\ncycle 20 X 21 line 11 addx -1\nx * cycle = 420")));
+        assert_eq!(debugger.execute("c").unwrap().lines().take(2).collect::<Vec<&str>>(),
+                   vec!["Breakpoint 2, line 30", "cycle 56 X 34 line 30 addx -15"]);
+        assert_eq!(debugger.execute("delete 2"), Ok(String::from("Deleted breakpoint line 30")));
+        assert_eq!(debugger.execute("break if x < 2"), Ok(String::from("Breakpoint 2: if x < 2")));
+        assert_eq!(debugger.execute("info"),
+                   Ok(String::from("Breakpoint 1: cycle 20\nBreakpoint 2: if x < 2\nWatch 1: x * cycle")));
+        assert_eq!(debugger.execute("c"),
+                   Ok(String::from("Breakpoint 2, if x < 2\ncycle 81 X 1 line 46 noop\nx * cycle = 81")));
+        assert_eq!(debugger.execute("delete 3"), Err(String::from("No number 3")));
+        assert_eq!(debugger.execute("break when x"),
+                   Err(String::from("Expected break cycle <n>, break line <n> or break if <expr>")));
+    }
+
+    #[test]
+    fn can_write_vcd() {
+        let mut out = vec![];
+        write_vcd(Cpu::new(parse_program("noop\naddx 3\naddx -5")), &Crt::default(), &mut out).unwrap();
+        let vcd = String::from_utf8(out).unwrap();
+        assert_eq!(vcd, "\
+$version aoc2022-rust day 10 $end
+$timescale 1ns $end
+$scope module handheld $end
+$var wire 1 ! clk $end
+$var integer 32 \" cycle $end
+$var integer 32 # x $end
+$var integer 32 $ line $end
+$var string 1 % instruction $end
+$var integer 32 & beam_x $end
+$var integer 32 ' beam_y $end
+$upscope $end
+$enddefinitions $end
+#0
+1!
+b1 \"
+b1 #
+b1 $
+snoop %
+b0 &
+b0 '
+#1
+0!
+#2
+1!
+b10 \"
+b10 $
+saddx_3 %
+b1 &
+#3
+0!
+#4
+1!
+b11 \"
+b10 &
+#5
+0!
+#6
+1!
+b100 \"
+b100 #
+b11 $
+saddx_-5 %
+b11 &
+#7
+0!
+#8
+1!
+b101 \"
+b100 &
+#9
+0!
+#10
+");
+    }
+}
@@ -1,89 +1,425 @@
 use aoc2022_rust::problem;
+use clap::Parser;
+use itertools::Itertools;
 use std::collections::HashMap;
+use std::fmt;
+
+/// Score a rock paper scissors strategy guide, or any other cyclic game
+#[derive(Parser, Debug)]
+struct Cli {
+    /// Shapes in cycle order, each beats the half of the others just before it
+    #[arg(long, default_value = "rock,paper,scissors", value_delimiter = ',')]
+    shapes: Vec<String>,
+    /// Letters the opponent uses for the shapes, in the same order
+    #[arg(long, default_value = "ABC")]
+    opponent: String,
+    /// Letters in the second column for the shapes in puzzle 1
+    #[arg(long, default_value = "XYZ")]
+    mine: String,
+    /// Letters in the second column for losing, drawing and winning in puzzle 2, defaults to
+    /// the first three of --mine
+    #[arg(long)]
+    outcomes: Option<String>,
+    /// Scores for playing each shape, defaults to 1 for the first shape, 2 for the next and so on
+    #[arg(long, value_delimiter = ',')]
+    shape_scores: Option<Vec<u32>>,
+    /// Scores for losing, drawing and winning a round
+    #[arg(long, default_value = "0,3,6", value_delimiter = ',')]
+    outcome_scores: Vec<u32>,
+    /// Score every way of reading the second column as shapes and the best and worst possible
+    #[arg(short, long)]
+    analyse: bool,
+}
 
 fn main() {
+    let args = Cli::parse();
+    let shapes: Vec<&str> = args.shapes.iter().map(String::as_str).collect();
+    let game = Game::new(&shapes, &args.opponent, &args.mine)
+        .and_then(|game| match &args.shape_scores {
+            Some(scores) => game.with_shape_scores(scores),
+            None => Ok(game),
+        })
+        .and_then(|game| game.with_outcome_scores(&args.outcome_scores))
+        .and_then(|game| match &args.outcomes {
+            Some(letters) => game.with_outcome_letters(letters),
+            None => Ok(game),
+        });
+    let game = match game {
+        Ok(game) => game,
+        Err(error) => return eprintln!("{}", error),
+    };
     let input = problem::load(2);
-    let solution_1 = solve1(&input);
-    println!("Puzzle 1 {}", solution_1);
-    let solution_2 = solve2(&input);
-    println!("Puzzle 2 {}", solution_2);
+    if args.analyse {
+        match parse_input(&input, &game).and_then(|rounds| analyse(&rounds, &game)) {
+            Ok(analysis) => println!("{}", analysis.describe(&game)),
+            Err(error) => eprintln!("{}", error),
+        }
+        return;
+    }
+    match solve1(&input, &game).and_then(|solution_1| Ok((solution_1, solve2(&input, &game)?))) {
+        Ok((solution_1, solution_2)) => {
+            println!("Puzzle 1 {}", solution_1);
+            println!("Puzzle 2 {}", solution_2);
+        }
+        Err(error) => eprintln!("{}", error),
+    }
 }
 
-fn solve1(input: &str) -> u32 {
-    let turns = parse_input(input);
+// A shape's position in the game's cycle. Games can have any odd number of
+// shapes so they're numbered rather than named, the names live in the Game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Shape(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Outcome {
+    Lose,
+    Draw,
+    Win,
+}
+
+impl Outcome {
+    const ALL: [Outcome; 3] = [Outcome::Lose, Outcome::Draw, Outcome::Win];
+}
 
-    let mut total_score = 0;
-    for turn in turns {
-        total_score += score_outcome(turn.0, turn.1);
-        total_score += score_value(turn.1);
+#[derive(Debug, PartialEq)]
+struct GameError(String);
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
     }
-    return total_score;
 }
 
-fn solve2(input: &str) -> u32 {
-    let turns = parse_input(input);
-    let mut total_score = 0;
-    for turn in turns {
-        total_score += turn.1;
-        total_score += score_my_value(turn.0, turn.1);
+// The rules and scoring of a cyclic game like rock paper scissors. Going round
+// the cycle each shape beats the half of the others that come just before it,
+// so with rock, paper, scissors paper beats rock and rock beats scissors.
+#[derive(Debug)]
+struct Game {
+    names: Vec<String>,
+    opponent_letters: HashMap<char, Shape>,
+    // Letters of the second column read as shapes then as outcomes
+    my_letters: Vec<char>,
+    outcome_letters: Vec<char>,
+    shape_scores: Vec<u32>,
+    outcome_scores: HashMap<Outcome, u32>,
+}
+
+impl Game {
+    fn new(names: &[&str], opponent_letters: &str, my_letters: &str) -> Result<Self, GameError> {
+        let n = names.len();
+        if n < 3 || n.is_multiple_of(2) {
+            return Err(GameError(format!("A fair cyclic game needs an odd number of shapes, at least 3, not {}", n)));
+        }
+        let opponent: Vec<char> = opponent_letters.chars().collect();
+        let mine: Vec<char> = my_letters.chars().collect();
+        if opponent.len() != n || mine.len() != n {
+            return Err(GameError(format!("Each column needs a letter for each of the {} shapes", n)));
+        }
+        for letters in [&opponent, &mine] {
+            if (1..n).any(|i| letters[..i].contains(&letters[i])) {
+                return Err(GameError(format!("Letters {:?} must all be different", letters.iter().collect::<String>())));
+            }
+        }
+        Ok(Game {
+            names: names.iter().map(|name| String::from(*name)).collect(),
+            opponent_letters: opponent.into_iter().enumerate().map(|(i, letter)| (letter, Shape(i))).collect(),
+            outcome_letters: mine[..3].to_vec(),
+            my_letters: mine,
+            shape_scores: (1..=n as u32).collect(),
+            outcome_scores: Outcome::ALL.into_iter().zip([0, 3, 6]).collect(),
+        })
+    }
+
+    fn with_shape_scores(mut self, scores: &[u32]) -> Result<Self, GameError> {
+        if scores.len() != self.names.len() {
+            return Err(GameError(format!("Expected a score for each of the {} shapes", self.names.len())));
+        }
+        self.shape_scores = scores.to_vec();
+        Ok(self)
+    }
+
+    fn with_outcome_letters(mut self, letters: &str) -> Result<Self, GameError> {
+        let letters: Vec<char> = letters.chars().collect();
+        if letters.len() != 3 || letters[0] == letters[1] || letters[1] == letters[2] || letters[0] == letters[2] {
+            return Err(GameError(String::from("Expected three different letters for losing, drawing and winning")));
+        }
+        self.outcome_letters = letters;
+        Ok(self)
     }
-    return total_score;
+
+    fn with_outcome_scores(mut self, scores: &[u32]) -> Result<Self, GameError> {
+        if scores.len() != 3 {
+            return Err(GameError(String::from("Expected scores for losing, drawing and winning")));
+        }
+        self.outcome_scores = Outcome::ALL.into_iter().zip(scores.iter().copied()).collect();
+        Ok(self)
+    }
+
+    fn no_shapes(&self) -> usize {
+        self.names.len()
+    }
+
+    fn outcome(&self, opponent: Shape, mine: Shape) -> Outcome {
+        let n = self.no_shapes();
+        match (mine.0 + n - opponent.0) % n {
+            0 => Outcome::Draw,
+            ahead if ahead <= n / 2 => Outcome::Win,
+            _ => Outcome::Lose,
+        }
+    }
+
+    // The shape to play for an outcome, the nearest one round the cycle when there's a choice
+    fn shape_for(&self, opponent: Shape, outcome: Outcome) -> Shape {
+        let n = self.no_shapes();
+        match outcome {
+            Outcome::Lose => Shape((opponent.0 + n - 1) % n),
+            Outcome::Draw => opponent,
+            Outcome::Win => Shape((opponent.0 + 1) % n),
+        }
+    }
+
+    fn score(&self, opponent: Shape, mine: Shape) -> u32 {
+        self.outcome_scores[&self.outcome(opponent, mine)] + self.shape_scores[mine.0]
+    }
+
+    // The second column's letter as a shape, as we first read the guide
+    fn my_shape(&self, letter: char) -> Option<Shape> {
+        self.my_letters.iter().position(|&c| c == letter).map(Shape)
+    }
+
+    // The second column's letter as an outcome, as the elf meant it
+    fn my_outcome(&self, letter: char) -> Option<Outcome> {
+        self.outcome_letters.iter().position(|&c| c == letter).map(|i| Outcome::ALL[i])
+    }
+}
+
+// A line of the strategy guide, the second column is read differently by each puzzle
+#[derive(Debug, PartialEq)]
+struct Round {
+    line: usize,
+    opponent: Shape,
+    letter: char,
 }
 
-fn parse_input(input: &str) -> Vec<(u32, u32)> {
-    let mut opponent_choice = HashMap::<&str, u32>::new();
-    opponent_choice.insert("A", 0);
-    opponent_choice.insert("B", 1);
-    opponent_choice.insert("C", 2);
+fn parse_input(input: &str, game: &Game) -> Result<Vec<Round>, GameError> {
+    input.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let letters: Vec<&str> = line.split_whitespace().collect();
+            let (opponent, letter) = match letters[..] {
+                [opponent, mine] if opponent.chars().count() == 1 && mine.chars().count() == 1 => {
+                    (opponent.chars().next().unwrap(), mine.chars().next().unwrap())
+                }
+                _ => return Err(GameError(format!("Expected two letters on line {}: {:?}", i + 1, line))),
+            };
+            let opponent = *game.opponent_letters.get(&opponent)
+                .ok_or_else(|| GameError(format!("Unknown opponent letter {} on line {}", opponent, i + 1)))?;
+            Ok(Round { line: i + 1, opponent, letter })
+        })
+        .collect()
+}
 
-    let mut outcome = HashMap::<&str, u32>::new();
-    outcome.insert("X", 0);
-    outcome.insert("Y", 3);
-    outcome.insert("Z", 6);
+fn unknown_letter(round: &Round) -> GameError {
+    GameError(format!("Unknown letter {} on line {}", round.letter, round.line))
+}
 
-    return input
-        .lines()
-        .map(|x| {
-            let mut split = x.split_whitespace();
-            return (*opponent_choice.get(split.next().unwrap()).unwrap(),
-                    *outcome.get(split.next().unwrap()).unwrap())
-        }).collect();
+fn solve1(input: &str, game: &Game) -> Result<u32, GameError> {
+    parse_input(input, game)?
+        .iter()
+        .map(|round| game.my_shape(round.letter)
+            .map(|mine| game.score(round.opponent, mine))
+            .ok_or_else(|| unknown_letter(round)))
+        .sum()
 }
 
-fn score_outcome(opponent: u32, mine: u32) -> u32 {
-    // With rock = 0, paper = 1, scissors = 2
-    // then if mine == opponent + 1 (mod 3) then I win
-    return if mine == opponent {
-        3
-    } else if mine == (opponent + 1) % 3 {
-        6
-    } else {
-        0
-    }
+fn solve2(input: &str, game: &Game) -> Result<u32, GameError> {
+    parse_input(input, game)?
+        .iter()
+        .map(|round| game.my_outcome(round.letter)
+            .map(|outcome| game.score(round.opponent, game.shape_for(round.opponent, outcome)))
+            .ok_or_else(|| unknown_letter(round)))
+        .sum()
 }
 
-fn score_value(mine: u32) -> u32 {
-    return mine + 1;
+// One way of reading the second column as shapes, with how it plays out
+#[derive(Debug, PartialEq)]
+struct Reading {
+    // Shape for each of the second column's letters in order
+    shapes: Vec<Shape>,
+    score: u32,
+    // Number of rounds lost, drawn and won
+    outcomes: [usize; 3],
 }
 
-fn score_my_value(opponent: u32, outcome: u32) -> u32 {
-    match outcome {
-        0 => return score_value((opponent + 2) % 3),
-        3 => return score_value(opponent),
-        6 => return score_value((opponent + 1) % 3),
-        _ => panic!("invalid value")
+#[derive(Debug, PartialEq)]
+struct Analysis {
+    // Scores from playing the best and worst shape every round
+    best: u32,
+    worst: u32,
+    // Every mapping of letters to shapes, in the order they're generated
+    readings: Vec<Reading>,
+    // Index into readings of the one the elf most likely meant
+    most_likely: usize,
+}
+
+// Every mapping of letters to shapes is tried, which is n! of them, so more shapes
+// than this would take far too long
+const MAX_ANALYSED_SHAPES: usize = 7;
+
+fn analyse(rounds: &[Round], game: &Game) -> Result<Analysis, GameError> {
+    let n = game.no_shapes();
+    if n > MAX_ANALYSED_SHAPES {
+        return Err(GameError(format!("Can only analyse games of up to {} shapes, not {}", MAX_ANALYSED_SHAPES, n)));
+    }
+    let letters = rounds.iter()
+        .map(|round| game.my_letters.iter().position(|&c| c == round.letter).ok_or_else(|| unknown_letter(round)))
+        .collect::<Result<Vec<usize>, GameError>>()?;
+    let scores = |opponent: Shape| (0..n).map(move |mine| game.score(opponent, Shape(mine)));
+    let best = rounds.iter().map(|round| scores(round.opponent).max().unwrap()).sum();
+    let worst = rounds.iter().map(|round| scores(round.opponent).min().unwrap()).sum();
+
+    let readings: Vec<Reading> = (0..n).permutations(n)
+        .map(|mapping| {
+            let shapes: Vec<Shape> = mapping.into_iter().map(Shape).collect();
+            let mut outcomes = [0; 3];
+            let mut score = 0;
+            for (round, &letter) in rounds.iter().zip(letters.iter()) {
+                let outcome = game.outcome(round.opponent, shapes[letter]);
+                outcomes[Outcome::ALL.iter().position(|&o| o == outcome).unwrap()] += 1;
+                score += game.score(round.opponent, shapes[letter]);
+            }
+            Reading { shapes, score, outcomes }
+        })
+        .collect();
+    // A guide is there to help us win, so the elf most likely meant whichever reading
+    // scores highest, and failing that the one winning the most rounds
+    let most_likely = readings.iter()
+        .enumerate()
+        .max_by(|(i, a), (j, b)| a.score.cmp(&b.score).then(a.outcomes[2].cmp(&b.outcomes[2])).then(j.cmp(i)))
+        .map(|(i, _)| i)
+        .unwrap();
+    Ok(Analysis { best, worst, readings, most_likely })
+}
+
+impl Analysis {
+    fn describe(&self, game: &Game) -> String {
+        let mapping = |reading: &Reading| reading.shapes.iter()
+            .zip(game.my_letters.iter())
+            .map(|(shape, letter)| format!("{}={}", letter, game.names[shape.0]))
+            .join(" ");
+        let mut lines = vec![format!("Best possible {}", self.best), format!("Worst possible {}", self.worst)];
+        for reading in self.readings.iter() {
+            let [lost, drawn, won] = reading.outcomes;
+            lines.push(format!("{} scores {} winning {} drawing {} losing {}", mapping(reading), reading.score, won, drawn, lost));
+        }
+        lines.push(format!("Most likely {}", mapping(&self.readings[self.most_likely])));
+        lines.join("\n")
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
+
+    impl Game {
+        fn rock_paper_scissors() -> Self {
+            Game::new(&["rock", "paper", "scissors"], "ABC", "XYZ").unwrap()
+        }
+    }
+
     #[test]
     fn it_works() {
         let input = problem::load_test(2);
-        let solution_1 = solve1(&input);
-        assert_eq!(solution_1, 15);
-        let solution_2 = solve2(&input);
-        assert_eq!(solution_2, 12);
+        let game = Game::rock_paper_scissors();
+        let solution_1 = solve1(&input, &game);
+        assert_eq!(solution_1, Ok(15));
+        let solution_2 = solve2(&input, &game);
+        assert_eq!(solution_2, Ok(12));
+    }
+
+    #[test]
+    fn can_play_cyclic_games() {
+        let game = Game::rock_paper_scissors();
+        let [rock, paper, scissors] = [Shape(0), Shape(1), Shape(2)];
+        assert_eq!(game.outcome(rock, paper), Outcome::Win);
+        assert_eq!(game.outcome(rock, scissors), Outcome::Lose);
+        assert_eq!(game.outcome(scissors, rock), Outcome::Win);
+        assert_eq!(game.outcome(paper, paper), Outcome::Draw);
+
+        // Each shape beats the two just before it in the cycle
+        let game = Game::new(&["rock", "spock", "paper", "lizard", "scissors"], "ABCDE", "VWXYZ").unwrap();
+        let [rock, spock, paper, lizard, scissors] = [0, 1, 2, 3, 4].map(Shape);
+        for (winner, loser) in [(paper, rock), (rock, scissors), (scissors, paper), (rock, lizard), (lizard, spock),
+                                (spock, scissors), (scissors, lizard), (lizard, paper), (paper, spock), (spock, rock)] {
+            assert_eq!(game.outcome(loser, winner), Outcome::Win);
+            assert_eq!(game.outcome(winner, loser), Outcome::Lose);
+        }
+        for shape in [rock, spock, paper, lizard, scissors] {
+            for outcome in Outcome::ALL {
+                assert_eq!(game.outcome(shape, game.shape_for(shape, outcome)), outcome);
+            }
+        }
+
+        let game = game.with_shape_scores(&[1, 5, 2, 4, 3]).unwrap()
+            .with_outcome_scores(&[0, 1, 2]).unwrap()
+            .with_outcome_letters("XYZ").unwrap();
+        // Spock beats rock, scissors draws
+        assert_eq!(solve1("A W\nE Z", &game), Ok(5 + 2 + 3 + 1));
+        // Losing to rock with scissors scores 3, drawing with rock 1 + 1 and beating it with spock 5 + 2
+        assert_eq!(solve2("A X\nA Y\nA Z", &game), Ok(3 + 1 + 1 + 5 + 2));
+    }
+
+    #[test]
+    fn can_analyse_guide() {
+        let input = problem::load_test(2);
+        let game = Game::rock_paper_scissors();
+        let analysis = analyse(&parse_input(&input, &game).unwrap(), &game).unwrap();
+        assert_eq!((analysis.best, analysis.worst), (24, 6));
+        let scores: Vec<u32> = analysis.readings.iter().map(|reading| reading.score).collect();
+        assert_eq!(scores, vec![15, 6, 15, 15, 15, 24]);
+        // The puzzle's first reading is the first of the mappings
+        assert_eq!(analysis.readings[0].score, solve1(&input, &game).unwrap());
+        assert_eq!(analysis.readings[analysis.most_likely], Reading {
+            shapes: vec![Shape(2), Shape(1), Shape(0)],
+            score: 24,
+            outcomes: [0, 0, 3],
+        });
+        assert_eq!(analysis.describe(&game), "\
+Best possible 24
+Worst possible 6
+X=rock Y=paper Z=scissors scores 15 winning 1 drawing 1 losing 1
+X=rock Y=scissors Z=paper scores 6 winning 0 drawing 0 losing 3
+X=paper Y=rock Z=scissors scores 15 winning 0 drawing 3 losing 0
+X=paper Y=scissors Z=rock scores 15 winning 1 drawing 1 losing 1
+X=scissors Y=rock Z=paper scores 15 winning 1 drawing 1 losing 1
+X=scissors Y=paper Z=rock scores 24 winning 3 drawing 0 losing 0
+Most likely X=scissors Y=paper Z=rock");
+
+        // Every reading lies between the worst and best possible
+        let game = Game::new(&["rock", "spock", "paper", "lizard", "scissors"], "ABCDE", "VWXYZ").unwrap();
+        let analysis = analyse(&parse_input("A V\nB W\nE Z\nC V\nD X", &game).unwrap(), &game).unwrap();
+        assert_eq!(analysis.readings.len(), 120);
+        assert!(analysis.readings.iter().all(|reading| (analysis.worst..=analysis.best).contains(&reading.score)));
+        assert_eq!(analysis.readings.iter().map(|reading| reading.score).max(), Some(analysis.readings[analysis.most_likely].score));
+
+        let game = Game::new(&["a", "b", "c", "d", "e", "f", "g", "h", "i"], "ABCDEFGHI", "RSTUVWXYZ").unwrap();
+        let error = analyse(&parse_input("A R", &game).unwrap(), &game).unwrap_err();
+        assert_eq!(error, GameError(String::from("Can only analyse games of up to 7 shapes, not 9")));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn rejects_bad_games_and_guides() {
+        assert!(Game::new(&["rock", "paper"], "AB", "XY").is_err());
+        assert!(Game::new(&["a", "b", "c", "d"], "ABCD", "WXYZ").is_err());
+        assert_eq!(Game::new(&["a", "b", "c"], "AAC", "XYZ").err(), Some(GameError(String::from("Letters \"AAC\" must all be different"))));
+        assert!(Game::new(&["a", "b", "c"], "AB", "XYZ").is_err());
+        assert!(Game::rock_paper_scissors().with_shape_scores(&[1, 2]).is_err());
+        assert!(Game::rock_paper_scissors().with_outcome_letters("XXZ").is_err());
+
+        let game = Game::rock_paper_scissors();
+        assert_eq!(solve1("A Y\nD X", &game), Err(GameError(String::from("Unknown opponent letter D on line 2"))));
+        assert_eq!(solve2("A Y\nB W", &game), Err(GameError(String::from("Unknown letter W on line 2"))));
+        assert_eq!(solve1("A YY", &game), Err(GameError(String::from("Expected two letters on line 1: \"A YY\""))));
+    }
+}
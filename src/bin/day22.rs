@@ -1,22 +1,77 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fmt;
+use std::fs;
 use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
 
+use clap::Parser;
 use itertools::iterate;
 use lazy_static::lazy_static;
 use regex::Regex;
 
 use aoc2022_rust::problem;
 
+/// Follow the monkeys' path around the board
+#[derive(Parser, Debug)]
+struct Cli {
+    /// Use the test input instead of the puzzle input
+    #[arg(short, long)]
+    test: bool,
+    /// Draw the board with the path walked when wrapping this way
+    #[arg(short, long, value_enum)]
+    render: Option<WrapKind>,
+    /// Colour the drawing for the terminal
+    #[arg(short, long)]
+    colour: bool,
+    /// Write an SVG drawing of the folded cube with the path walked over it
+    #[arg(long)]
+    svg: Option<PathBuf>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum WrapKind {
+    Flat,
+    Cube,
+}
+
 fn main() {
-    let input = problem::load_raw(22);
-    let solution_1 = solve1(&input);
+    let args = Cli::parse();
+    let input = if args.test { problem::load_raw_test(22) } else { problem::load_raw(22) };
+    let (board, instructions) = match parse_inputs(&input) {
+        Ok(parsed) => parsed,
+        Err(error) => return eprintln!("{}", error),
+    };
+    if let Some(kind) = args.render {
+        let wrap = match kind {
+            WrapKind::Flat => Wrap::Flat,
+            WrapKind::Cube => match Cube::from_board(&board) {
+                Ok(cube) => Wrap::Cube(cube),
+                Err(error) => return eprintln!("{}", error),
+            },
+        };
+        let path = walk_path(&board, &instructions, &Jumps::new(&board, &wrap));
+        println!("{}", render_path(&board, &path, args.colour));
+    }
+    if let Some(file) = &args.svg {
+        let (graph, face_size) = match fold_board(&board) {
+            Ok(folded) => folded,
+            Err(error) => return eprintln!("{}", error),
+        };
+        let jumps = Jumps::new(&board, &Wrap::Cube(Cube::new(&graph, face_size)));
+        let path = walk_path(&board, &instructions, &jumps);
+        fs::write(file, render_cube_svg(&board, &graph, face_size, &path))
+            .unwrap_or_else(|_| panic!("Error writing file {}", file.display()));
+    }
+    let solution_1 = solve1(&board, &instructions);
     println!("Puzzle 1 {}", solution_1);
-//     let solution_2 = solve2(&input);
-//     println!("Puzzle 2 {}", solution_2);
+    match solve2(&board, &instructions) {
+        Ok(solution_2) => println!("Puzzle 2 {}", solution_2),
+        Err(error) => eprintln!("{}", error),
+    }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum Instruction {
     RotateL,
     RotateR,
@@ -30,13 +85,34 @@ enum Tile {
     Open,
 }
 
+// Rows are padded with Empty tiles so each has no_cols of them. The input's lines
+// don't have trailing spaces, so short rows are expected rather than an error.
 #[derive(Debug)]
 struct Board {
     tiles: Vec<Vec<Tile>>,
     no_cols: usize,
     no_rows: usize,
+    // The leftmost open tile of the top row, where the path starts
+    start_col: usize,
 }
 
+impl Board {
+    fn new(mut tiles: Vec<Vec<Tile>>) -> Result<Self, ParseError> {
+        if tiles.iter().flatten().all(|tile| *tile == Tile::Empty) {
+            return Err(ParseError::EmptyBoard);
+        }
+        let no_cols = tiles.iter().map(|row| row.len()).max().unwrap_or(0);
+        let start_col = tiles[0].iter()
+            .position(|tile| *tile == Tile::Open)
+            .ok_or(ParseError::NoStartTile)?;
+        for row in tiles.iter_mut() {
+            row.resize_with(no_cols, || Tile::Empty);
+        }
+        Ok(Board { no_rows: tiles.len(), tiles, no_cols, start_col })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
 struct State {
     row: usize,
     col: usize,
@@ -46,10 +122,9 @@ struct State {
 
 impl State {
     pub fn new(board: &Board) -> Self {
-        let start_col = get_start_col(&board.tiles);
         State {
             row: 0,
-            col: start_col,
+            col: board.start_col,
             direction: 0,
         }
     }
@@ -85,7 +160,54 @@ fn move_one(&State { row, col, direction }: &State, board: &Board) -> State {
     }
 }
 
-fn run_instruction(state: State, instruction: Instruction, board: &Board) -> State {
+fn get_tile(board: &Board, row: usize, col: usize) -> &Tile {
+    board.tiles.get(row).and_then(|tiles| tiles.get(col)).unwrap_or(&Tile::Empty)
+}
+
+// How walking off the edge of the board brings us back onto it
+enum Wrap {
+    // Come back on at the opposite side of the same row or column
+    Flat,
+    // Fold the board into a cube and walk onto the adjacent face
+    Cube(Cube),
+}
+
+// For every open tile and facing, where one step forward ends up once any wrap
+// around the board is taken into account. Stepping into a wall stays put.
+struct Jumps {
+    no_cols: usize,
+    next: Vec<[State; 4]>,
+}
+
+impl Jumps {
+    fn new(board: &Board, wrap: &Wrap) -> Self {
+        let blank = State { row: 0, col: 0, direction: 0 };
+        let mut next = vec![[blank; 4]; board.no_rows * board.no_cols];
+        for (row, tiles) in board.tiles.iter().enumerate() {
+            for (col, _) in tiles.iter().enumerate().filter(|(_, tile)| **tile == Tile::Open) {
+                for direction in 0..4 {
+                    let state = State { row, col, direction };
+                    let target = iterate(state, |state| match wrap {
+                        Wrap::Flat => move_one(state, board),
+                        Wrap::Cube(cube) => cube.move_one(state, board),
+                    })
+                        .skip(1)
+                        .find(|s| *get_tile(board, s.row, s.col) != Tile::Empty)
+                        .unwrap();
+                    let is_open = *get_tile(board, target.row, target.col) == Tile::Open;
+                    next[row * board.no_cols + col][direction as usize] = if is_open { target } else { state };
+                }
+            }
+        }
+        Jumps { no_cols: board.no_cols, next }
+    }
+
+    fn step(&self, state: &State) -> State {
+        self.next[state.row * self.no_cols + state.col][state.direction as usize]
+    }
+}
+
+fn run_instruction(state: State, instruction: Instruction, jumps: &Jumps) -> State {
     match instruction {
         Instruction::RotateR => {
             State {
@@ -102,96 +224,182 @@ fn run_instruction(state: State, instruction: Instruction, board: &Board) -> Sta
             }
         }
         Instruction::Move(steps) => {
-            iterate(state, |state| move_one(state, board))
-                .filter(|s| *board.tiles[s.row].get(s.col).unwrap_or(&Tile::Empty) != Tile::Empty)
-                .take(steps + 1)
-                .take_while(|s| board.tiles[s.row][s.col] == Tile::Open)
-                .last()
-                .unwrap()
+            let mut state = state;
+            for _ in 0..steps {
+                let next = jumps.step(&state);
+                if next == state {
+                    break;
+                }
+                state = next;
+            }
+            state
         }
     }
 }
 
-fn get_start_col(tiles: &Vec<Vec<Tile>>) -> usize {
-    tiles.first().unwrap().iter().position(|tile| matches!(tile, Tile::Open)).unwrap()
+// Every state along the way from the start, one per step or turn
+fn walk_path(board: &Board, instructions: &[Instruction], jumps: &Jumps) -> Vec<State> {
+    let mut path = vec![State::new(board)];
+    for instruction in instructions {
+        let state = *path.last().unwrap();
+        match *instruction {
+            Instruction::Move(steps) => {
+                for _ in 0..steps {
+                    let state = *path.last().unwrap();
+                    let next = run_instruction(state, Instruction::Move(1), jumps);
+                    if next == state {
+                        break;
+                    }
+                    path.push(next);
+                }
+            }
+            turn => path.push(run_instruction(state, turn, jumps)),
+        }
+    }
+    path
+}
+
+const FACING: [char; 4] = ['>', 'v', '<', '^'];
+
+// Draws the board with each tile on the path marked by the way it was last
+// facing there, as in the puzzle. Colouring picks out the path, highlighting
+// where it ends.
+fn render_path(board: &Board, path: &[State], colour: bool) -> String {
+    let facing: HashMap<(usize, usize), i8> = path.iter()
+        .map(|state| ((state.row, state.col), state.direction))
+        .collect();
+    let last = path.last().map(|state| (state.row, state.col));
+    board.tiles.iter()
+        .enumerate()
+        .map(|(row, tiles)| tiles.iter()
+            .enumerate()
+            .map(|(col, tile)| {
+                let c = match (facing.get(&(row, col)), tile) {
+                    (Some(&direction), _) => FACING[direction as usize],
+                    (None, Tile::Empty) => ' ',
+                    (None, Tile::Solid) => '#',
+                    (None, Tile::Open) => '.',
+                };
+                match (colour, c) {
+                    (false, _) | (true, ' ' | '.') => c.to_string(),
+                    (true, '#') => format!("\x1b[90m{}\x1b[0m", c),
+                    _ if last == Some((row, col)) => format!("\x1b[1;31m{}\x1b[0m", c),
+                    _ => format!("\x1b[33m{}\x1b[0m", c),
+                }
+            })
+            .collect::<String>()
+            .trim_end()
+            .to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    // There's no blank line followed by the path after the board
+    NoPath,
+    // The board has no tiles on it
+    EmptyBoard,
+    // The top row has no open tile to start on
+    NoStartTile,
+    // Lines are numbered from 1
+    InvalidTile { line: usize, column: usize, tile: char },
+    // Position in the path, numbered from 1
+    InvalidInstruction { column: usize, token: String },
+    // Something after the path, which has to be on a single line
+    TrailingInput { line: usize },
 }
 
-fn parse_instructions(line: &str) -> Vec<Instruction> {
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::NoPath => write!(f, "Expected the path after a blank line following the board"),
+            ParseError::EmptyBoard => write!(f, "The board has no tiles"),
+            ParseError::NoStartTile => write!(f, "There's no open tile on the top row to start from"),
+            ParseError::InvalidTile { line, column, tile } => {
+                write!(f, "Invalid tile {:?} on line {} column {}", tile, line, column)
+            }
+            ParseError::InvalidInstruction { column, token } => {
+                write!(f, "Invalid instruction {:?} at column {} of the path", token, column)
+            }
+            ParseError::TrailingInput { line } => write!(f, "Unexpected input on line {} after the path", line),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn parse_instructions(line: &str) -> Result<Vec<Instruction>, ParseError> {
     lazy_static! {
-        static ref RE: Regex = Regex::new(r"([0-9]+|R|L)").unwrap();
+        static ref RE: Regex = Regex::new(r"[0-9]+|R|L|.").unwrap();
     }
-    ;
     RE.find_iter(line)
         .map(|m| {
-            let matched_str = m.as_str();
-            if matched_str.starts_with("R") {
-                Instruction::RotateR
-            } else if matched_str.starts_with("L") {
-                Instruction::RotateL
-            } else {
-                Instruction::Move(matched_str.parse().unwrap())
+            let invalid = || ParseError::InvalidInstruction {
+                column: line[..m.start()].chars().count() + 1,
+                token: String::from(m.as_str()),
+            };
+            match m.as_str() {
+                "R" => Ok(Instruction::RotateR),
+                "L" => Ok(Instruction::RotateL),
+                steps => steps.parse().map(Instruction::Move).map_err(|_| invalid()),
             }
         })
         .collect()
 }
 
-fn parse_inputs(input: &str) -> (Board, Vec<Instruction>) {
-    let mut tiles = vec![];
-    let mut instructions: Vec<Instruction> = vec![];
-    let mut iter = input.lines().peekable();
-    let mut no_cols = 0;
-    let mut no_rows = 0;
-    while let Some(line) = iter.next() {
-        if iter.peek().is_none() {
-            instructions = parse_instructions(line);
-            break;
-        }
-        let mut row: Vec<Tile> = vec![];
-        for c in line.chars() {
-            if c == ' ' {
-                row.push(Tile::Empty);
-            } else if c == '#' {
-                row.push(Tile::Solid);
-            } else if c == '.' {
-                row.push(Tile::Open);
-            } else {
-                panic!("Invalid char encountered {}", c);
-            }
-        }
-        let no_chars = line.chars().count();
-        if no_chars > no_cols {
-            no_cols = no_chars;
-        }
-        if row.len() > 0 {
-            tiles.push(row);
-            no_rows += 1;
-        }
-    }
-    (Board { tiles, no_cols, no_rows }, instructions)
+fn parse_tiles(line_no: usize, line: &str) -> Result<Vec<Tile>, ParseError> {
+    line.chars()
+        .enumerate()
+        .map(|(column, c)| match c {
+            ' ' => Ok(Tile::Empty),
+            '#' => Ok(Tile::Solid),
+            '.' => Ok(Tile::Open),
+            tile => Err(ParseError::InvalidTile { line: line_no, column: column + 1, tile }),
+        })
+        .collect()
 }
 
-fn solve1(input: &str) -> usize {
-    let (board, instructions) = parse_inputs(input);
-    let end_state = instructions.into_iter().fold(
-        State::new(&board),
-        |state, instruction| run_instruction(state, instruction, &board));
-    return end_state.get_final_password();
+// The board comes first then a blank line and the path on a single line
+fn parse_inputs(input: &str) -> Result<(Board, Vec<Instruction>), ParseError> {
+    let lines: Vec<&str> = input.lines().map(|line| line.trim_end_matches('\r')).collect();
+    let blank = lines.iter().position(|line| line.trim().is_empty()).ok_or(ParseError::NoPath)?;
+    let tiles = lines[..blank].iter()
+        .enumerate()
+        .map(|(row, line)| parse_tiles(row + 1, line.trim_end()))
+        .collect::<Result<Vec<Vec<Tile>>, ParseError>>()?;
+    let board = Board::new(tiles)?;
+
+    // Lines after the board paired with their line numbers
+    let path: Vec<(usize, &str)> = lines.iter().enumerate()
+        .skip(blank)
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| (index + 1, *line))
+        .collect();
+    match path[..] {
+        [(_, path)] => Ok((board, parse_instructions(path.trim())?)),
+        [] => Err(ParseError::NoPath),
+        [_, (line, _), ..] => Err(ParseError::TrailingInput { line }),
+    }
 }
 
-// fn solve2(input: &str) -> u32 {
-//     let mut lines = input.lines().peekable();
-//     let mut sum: u32 = 0;
-//     // Relying on that fact that if we can get 1 we can get 3 as this is a nice puzzle
-//     while lines.peek().is_some() {
-//         sum += get_priority(get_common_items(lines.next().unwrap(),
-//                                              lines.next().unwrap(),
-//                                              lines.next().unwrap()));
-//     }
-//     return sum;
-// }
+fn solve1(board: &Board, instructions: &[Instruction]) -> usize {
+    let jumps = Jumps::new(board, &Wrap::Flat);
+    let end_state = instructions.iter().fold(
+        State::new(board),
+        |state, &instruction| run_instruction(state, instruction, &jumps));
+    end_state.get_final_password()
+}
 
+fn solve2(board: &Board, instructions: &[Instruction]) -> Result<usize, NetError> {
+    let jumps = Jumps::new(board, &Wrap::Cube(Cube::from_board(board)?));
+    let end_state = instructions.iter().fold(
+        State::new(board),
+        |state, &instruction| run_instruction(state, instruction, &jumps));
+    Ok(end_state.get_final_password())
+}
 
-#[derive(Clone, Debug, Eq, Hash)]
+#[derive(Clone, Debug, Eq)]
 struct Node {
     id: String,
     loc: (u32, u32),
@@ -203,12 +411,10 @@ impl PartialEq for Node {
     }
 }
 
-fn equivalent_node(a: Node, b: Node, edge_length: u32) -> bool {
-    a.id != b.id &&
-        (a.loc.0 == b.loc.0 && a.loc.1 == b.loc.1 + edge_length || // A above b
-            a.loc.0 == b.loc.0 && a.loc.1 + edge_length == b.loc.1 || // B above A
-            a.loc.0 == b.loc.0 + edge_length && a.loc.1 == b.loc.1 || // A left of B
-            a.loc.0 + edge_length == b.loc.0 && a.loc.1 == b.loc.1)   // B left of A
+impl Hash for Node {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
 }
 
 #[derive(Clone, Debug, Eq)]
@@ -217,68 +423,45 @@ struct Edge {
     b: Node,
 }
 
-#[derive(Clone, Debug, Eq)]
-struct EquivalentNode {
-    a: Node,
-    b: Node,
-}
-
-impl<'a> PartialEq for EquivalentNode {
-    fn eq(&self, other: &Self) -> bool {
-        self.a == other.a && self.b == other.b || self.a == other.b && self.b == other.a
+impl Edge {
+    fn has_node(&self, node: &Node) -> bool {
+        self.a == *node || self.b == *node
     }
-}
 
-impl<'a> Hash for EquivalentNode {
-    fn hash<H: Hasher>(&self, state: &mut H) {
+    fn sorted_ids(&self) -> (&str, &str) {
         if self.a.id < self.b.id {
-            self.a.id.hash(state);
-            self.b.id.hash(state);
+            (&self.a.id, &self.b.id)
         } else {
-            self.b.id.hash(state);
-            self.a.id.hash(state);
+            (&self.b.id, &self.a.id)
         }
     }
 }
 
-fn identify_nodes<'a>(equivalent_nodes: &HashSet<EquivalentNode>, x: &'a Edge, y: &'a Edge) -> Option<(Node, EquivalentNode)> {
-    let mut all_x_a = vec![x.a.clone()];
-    let mut all_x_b = vec![x.b.clone()];
-    for node in equivalent_nodes {
-        if node.a == x.a {
-            all_x_a.push(node.b.clone());
-        } else if node.b == x.a {
-            all_x_a.push(node.a.clone());
-        }
-
-        if node.a == x.b {
-            all_x_b.push(node.b.clone());
-        } else if node.b == x.b {
-            all_x_b.push(node.a.clone());
-        }
+impl PartialEq for Edge {
+    fn eq(&self, other: &Self) -> bool {
+        self.sorted_ids() == other.sorted_ids()
     }
+}
 
-    if all_x_a.contains(&y.a) {
-        Some((x.a.clone(), EquivalentNode { a: x.b.clone(), b: y.b.clone() }))
-    } else if all_x_a.contains(&y.b) {
-        Some((x.a.clone(), EquivalentNode { a: x.b.clone(), b: y.a.clone() }))
-    } else if all_x_b.contains(&y.a) {
-        Some((x.b.clone(), EquivalentNode { a: x.a.clone(), b: y.b.clone() }))
-    } else if all_x_b.contains(&y.b) {
-        Some((x.b.clone(), EquivalentNode { a: x.a.clone(), b: y.a.clone() }))
-    } else {
-        None
+impl Hash for Edge {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.sorted_ids().hash(state);
     }
 }
 
-impl<'a> PartialEq for Edge {
+#[derive(Clone, Debug, Eq)]
+struct EquivalentNode {
+    a: Node,
+    b: Node,
+}
+
+impl PartialEq for EquivalentNode {
     fn eq(&self, other: &Self) -> bool {
-        self.a.id == other.a.id && self.b.id == other.b.id ||
-            self.a.id == other.b.id && self.b.id == other.a.id
+        self.a == other.a && self.b == other.b || self.a == other.b && self.b == other.a
     }
 }
 
-impl<'a> Hash for Edge {
+impl Hash for EquivalentNode {
     fn hash<H: Hasher>(&self, state: &mut H) {
         if self.a.id < self.b.id {
             self.a.id.hash(state);
@@ -290,135 +473,604 @@ impl<'a> Hash for Edge {
     }
 }
 
+// Two edges of the net that become the same edge of the folded cube
 #[derive(Debug, Eq, Clone)]
-struct EquivalentEdges<'a> {
-    edge_1: &'a Edge,
-    edge_2: &'a Edge,
+struct EquivalentEdges {
+    edge_1: Edge,
+    edge_2: Edge,
 }
 
-impl<'a> Hash for EquivalentEdges<'a> {
+impl Hash for EquivalentEdges {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        if self.edge_1.a.id < self.edge_2.a.id || self.edge_1.b.id < self.edge_2.b.id {
-            self.edge_1.hash(state);
-            self.edge_2.hash(state);
+        let (id_1, id_2) = (self.edge_1.sorted_ids(), self.edge_2.sorted_ids());
+        if id_1 < id_2 {
+            id_1.hash(state);
+            id_2.hash(state);
         } else {
-            self.edge_2.hash(state);
-            self.edge_1.hash(state);
+            id_2.hash(state);
+            id_1.hash(state);
         }
     }
 }
 
-impl<'a> PartialEq for EquivalentEdges<'a> {
+impl PartialEq for EquivalentEdges {
     fn eq(&self, other: &Self) -> bool {
         self.edge_1 == other.edge_1 && self.edge_2 == other.edge_2 ||
             self.edge_1 == other.edge_2 && self.edge_2 == other.edge_1
     }
 }
 
+// The corners and edges of the faces in a cube net, with loc in units of faces.
+// Folding works out which corners and edges of the net meet on the cube.
 #[derive(Clone)]
-struct Graph<'a> {
-    graph: HashMap<&'a str, Vec<Edge>>,
-    equivalent_edges: HashSet<EquivalentEdges<'a>>,
+struct Graph {
+    graph: HashMap<String, Vec<Edge>>,
+    equivalent_edges: HashSet<EquivalentEdges>,
     equivalent_nodes: HashSet<EquivalentNode>,
 }
 
-impl<'a> Graph<'a> {
-    fn get_unfolded_edges(&self) -> HashMap<&&str, &Vec<Edge>> {
-        self.graph.iter().filter(|&(_, edges)| edges.len() < 4).collect()
+impl Graph {
+    // Builds the graph for faces whose top left corners are at the given locations
+    fn from_faces(faces: &[(u32, u32)]) -> Graph {
+        let node = |x: u32, y: u32| Node { id: format!("{},{}", x, y), loc: (x, y) };
+        let mut graph: HashMap<String, Vec<Edge>> = HashMap::new();
+        for &(x, y) in faces {
+            let corners = [node(x, y), node(x + 1, y), node(x + 1, y + 1), node(x, y + 1)];
+            for i in 0..4 {
+                let edge = Edge { a: corners[i].clone(), b: corners[(i + 1) % 4].clone() };
+                for end in [&edge.a, &edge.b] {
+                    let edges = graph.entry(end.id.clone()).or_default();
+                    if !edges.contains(&edge) {
+                        edges.push(edge.clone());
+                    }
+                }
+            }
+        }
+        Graph { graph, equivalent_edges: HashSet::new(), equivalent_nodes: HashSet::new() }
     }
 
-    // fn append_edges(&'a mut self, key: &str, edges: &'a Vec<&'a Edge>) {
-    //     let current_edges = self.graph.get_mut(key).unwrap();
-    //     for edge in edges {
-    //         if !current_edges.contains(edge) {
-    //             current_edges.push(edge);
-    //         }
-    //     }
-    //     let edge = Edge { a: &n, b: &Node { id: String::from("c"), loc: (3, 2) } };
-    //     current_edges.push(&edge);
-    // }
+    fn nodes(&self) -> HashMap<(u32, u32), Node> {
+        self.graph.values()
+            .flatten()
+            .flat_map(|edge| [edge.a.clone(), edge.b.clone()])
+            .map(|node| (node.loc, node))
+            .collect()
+    }
 
-    // fn append_duplicate(&mut self, edges: Vec<(&'a Edge, &'a Edge)>) {
-    //     for (edge_a, edge_b) in edges {
-    //         self.duplicate_edges.push((edge_a.clone(), edge_b.clone()))
-    //     }
-    // }
+    fn has_edge(&self, a: &Node, b: &Node) -> bool {
+        self.graph.get(&a.id).is_some_and(|edges| edges.iter().any(|edge| edge.has_node(b)))
+    }
 
+    // Every face as its corners clockwise from the top left. Any square with all
+    // four edges is a face as a net can't surround a hole.
+    fn faces(&self) -> Vec<[Node; 4]> {
+        let nodes = self.nodes();
+        let mut faces = vec![];
+        for (&(x, y), top_left) in nodes.iter() {
+            let corners = (nodes.get(&(x + 1, y)), nodes.get(&(x + 1, y + 1)), nodes.get(&(x, y + 1)));
+            if let (Some(top_right), Some(bottom_right), Some(bottom_left)) = corners {
+                let face = [top_left.clone(), top_right.clone(), bottom_right.clone(), bottom_left.clone()];
+                if (0..4).all(|i| self.has_edge(&face[i], &face[(i + 1) % 4])) {
+                    faces.push(face);
+                }
+            }
+        }
+        faces.sort_by_key(|face| (face[0].loc.1, face[0].loc.0));
+        faces
+    }
 
-    fn fold_graph(&'a self) -> Graph<'a> {
-        let mut equivalent_edges = self.equivalent_edges.clone();
-        let mut equivalent_nodes = self.equivalent_nodes.clone();
-        let mut graph = self.graph.clone();
-        let unfolded = self.get_unfolded_edges();
-        for (&key_x, &edges_x) in unfolded.iter() {
-            for (&key_y, &edges_y) in unfolded.iter() {
-                if *key_x != *key_y {
-                    for edge_x in edges_x {
-                        for edge_y in edges_y {
-                            let node_x = Node { id: String::from(*key_x), loc: (0, 0) };
-                            let node_y = Node { id: String::from(*key_y), loc: (0, 0) };
-                            if edge_x != edge_y &&
-                                !self.equivalent_edges.contains(&EquivalentEdges { edge_1: edge_x, edge_2: edge_y }) &&
-                                !self.equivalent_nodes.contains(&EquivalentNode { a: node_x, b: node_y }) {
-                                // println!("Testing edges {:?}, {:?},", edge_x, edge_y);
-                                if let Some((common, equivalent)) = identify_nodes(&self.equivalent_nodes, edge_x, edge_y) {
-                                    println!("common node, {}, has size {}", common.id, self.graph.get(common.id.as_str()).unwrap().len());
-                                    if self.graph.get(common.id.as_str()).unwrap().len() == 4 {
-                                        println!("Adding edges {:?}, {:?},", edge_x, edge_y);
-                                        // In this case edge x and edge y must be the same edge
-                                        // in the folded cube.
-                                        // We can add them to our list of duplicates and
-                                        // add their edges to each other
-                                        equivalent_edges.insert(EquivalentEdges { edge_1: edge_x, edge_2: edge_y });
-                                        equivalent_nodes.insert(equivalent.clone());
-                                        graph = fold_edges(graph, equivalent.clone(), key_y, edges_x);
-                                        graph = fold_edges(graph, equivalent, key_x, edges_y);
-                                    }
-                                }
-                            }
+    // Edges on the outside of the net, these are the ones that get glued together
+    fn boundary_edges(&self) -> Vec<Edge> {
+        let mut face_count: HashMap<Edge, usize> = HashMap::new();
+        for face in self.faces() {
+            for i in 0..4 {
+                *face_count.entry(Edge { a: face[i].clone(), b: face[(i + 1) % 4].clone() }).or_default() += 1;
+            }
+        }
+        let mut edges: Vec<Edge> = face_count.into_iter()
+            .filter(|&(_, count)| count == 1)
+            .map(|(edge, _)| edge)
+            .collect();
+        edges.sort_by(|x, y| x.sorted_ids().cmp(&y.sorted_ids()));
+        edges
+    }
+
+    fn is_glued(&self, edge: &Edge) -> bool {
+        self.equivalent_edges.iter().any(|pair| pair.edge_1 == *edge || pair.edge_2 == *edge)
+    }
+
+    // Groups nodes that are the same corner of the cube, mapping id to a group number
+    fn node_classes(&self) -> HashMap<String, usize> {
+        let mut classes: HashMap<String, usize> = self.graph.keys()
+            .enumerate()
+            .map(|(class, id)| (id.clone(), class))
+            .collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for pair in self.equivalent_nodes.iter() {
+                let (class_a, class_b) = (classes[&pair.a.id], classes[&pair.b.id]);
+                if class_a != class_b {
+                    let class = class_a.min(class_b);
+                    for value in classes.values_mut() {
+                        if *value == class_a || *value == class_b {
+                            *value = class;
                         }
                     }
+                    changed = true;
+                }
+            }
+        }
+        classes
+    }
+
+    // One round of folding. Three faces meet at every corner of a cube, so once a
+    // corner of the net has three faces around it the two unglued edges leaving
+    // it must be the same edge of the cube, which makes their far ends the same corner.
+    fn fold_graph(&self) -> Graph {
+        let mut equivalent_edges = self.equivalent_edges.clone();
+        let mut equivalent_nodes = self.equivalent_nodes.clone();
+        let classes = self.node_classes();
+        let faces = self.faces();
+        let unglued: Vec<Edge> = self.boundary_edges()
+            .into_iter()
+            .filter(|edge| !self.is_glued(edge))
+            .collect();
+
+        let mut corners: Vec<usize> = classes.values().copied().collect();
+        corners.sort();
+        corners.dedup();
+        for corner in corners {
+            let in_corner = |node: &Node| classes[&node.id] == corner;
+            let no_faces = faces.iter().filter(|face| face.iter().any(in_corner)).count();
+            let edges: Vec<&Edge> = unglued.iter()
+                .filter(|edge| in_corner(&edge.a) || in_corner(&edge.b))
+                .collect();
+            if no_faces == 3 && edges.len() == 2 {
+                let far_end = |edge: &Edge| if in_corner(&edge.a) { edge.b.clone() } else { edge.a.clone() };
+                let (far_1, far_2) = (far_end(edges[0]), far_end(edges[1]));
+                if far_1 != far_2 {
+                    equivalent_nodes.insert(EquivalentNode { a: far_1, b: far_2 });
                 }
+                equivalent_edges.insert(EquivalentEdges { edge_1: edges[0].clone(), edge_2: edges[1].clone() });
             }
         }
-        Graph { graph, equivalent_edges, equivalent_nodes }
+        Graph { graph: self.graph.clone(), equivalent_edges, equivalent_nodes }
+    }
+
+    // Folds until nothing more can be glued
+    fn fold(&self) -> Graph {
+        let mut graph = self.fold_graph();
+        let mut no_glued = self.equivalent_edges.len();
+        while graph.equivalent_edges.len() > no_glued {
+            no_glued = graph.equivalent_edges.len();
+            graph = graph.fold_graph();
+        }
+        graph
     }
 }
 
-fn fold_edges<'a>(mut graph: HashMap<&'a str, Vec<Edge>>, equivalent_node: EquivalentNode, key: &str, edges: &'a Vec<Edge>) -> HashMap<&'a str, Vec<Edge>> {
-    println!("folding edges for key {:?}, with eqiv nodes {:?} and edges {:?}", key, equivalent_node, edges);
-    let current_edges = graph.get_mut(key).unwrap();
-    let (fold_to, fold_from) = if equivalent_node.a.id == key {
-        (equivalent_node.a.clone(), equivalent_node.b.clone())
-    } else {
-        (equivalent_node.b.clone(), equivalent_node.a.clone())
-    };
+// Where walking off a side of a face comes back onto the cube. Sides are numbered
+// like directions, 0 is the right side, 1 the bottom, 2 the left and 3 the top.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Seam {
+    face: (u32, u32),
+    side: i8,
+    // Sides run clockwise around their face, if reversed the position along the
+    // side we leave by is counted from the other end of the side we arrive on
+    reversed: bool,
+}
 
+struct Cube {
+    face_size: usize,
+    seams: HashMap<((u32, u32), i8), Seam>,
+}
 
-    for edge in edges {
-        let new: Edge = if edge.a.id == fold_from.id {
-            Edge { a: fold_to.clone(), b: edge.b.clone() }
-        } else {
-            Edge { a: fold_to.clone(), b: edge.a.clone() }
+impl Cube {
+    // Builds the seams from a fully folded graph of the net
+    fn new(graph: &Graph, face_size: usize) -> Self {
+        let faces = graph.faces();
+        let classes = graph.node_classes();
+        // Finds the face and side an edge is on, along with the corners of the
+        // side in clockwise order
+        let locate = |edge: &Edge| {
+            faces.iter()
+                .find_map(|face| (0..4).find_map(|i| {
+                    let side = Edge { a: face[i].clone(), b: face[(i + 1) % 4].clone() };
+                    // Clockwise from the top left we go over the top, right, bottom then left
+                    (side == *edge).then(|| (face[0].loc, (i as i8 + 3) % 4, side.a, side.b))
+                }))
+                .unwrap()
+        };
+        let mut seams = HashMap::new();
+        for pair in graph.equivalent_edges.iter() {
+            let (face_1, side_1, start_1, _) = locate(&pair.edge_1);
+            let (face_2, side_2, start_2, _) = locate(&pair.edge_2);
+            let reversed = classes[&start_1.id] != classes[&start_2.id];
+            seams.insert((face_1, side_1), Seam { face: face_2, side: side_2, reversed });
+            seams.insert((face_2, side_2), Seam { face: face_1, side: side_1, reversed });
+        }
+        Cube { face_size, seams }
+    }
+
+    fn from_board(board: &Board) -> Result<Self, NetError> {
+        let (graph, face_size) = fold_board(board)?;
+        Ok(Cube::new(&graph, face_size))
+    }
+
+    fn move_one(&self, state: &State, board: &Board) -> State {
+        let n = self.face_size;
+        let (row, col) = match state.direction {
+            0 => (Some(state.row), state.col.checked_add(1)),
+            1 => (state.row.checked_add(1), Some(state.col)),
+            2 => (Some(state.row), state.col.checked_sub(1)),
+            3 => (state.row.checked_sub(1), Some(state.col)),
+            _ => panic!("Unknown direction")
+        };
+        if let (Some(row), Some(col)) = (row, col) {
+            if *get_tile(board, row, col) != Tile::Empty {
+                return State { row, col, direction: state.direction };
+            }
+        }
+
+        // Walked off the net so carry on from the face glued to this side
+        let face = ((state.col / n) as u32, (state.row / n) as u32);
+        let (r, c) = (state.row % n, state.col % n);
+        let along = match state.direction {
+            0 => r,
+            1 => n - 1 - c,
+            2 => n - 1 - r,
+            _ => c,
+        };
+        let seam = self.seams[&(face, state.direction)];
+        let along = if seam.reversed { n - 1 - along } else { along };
+        let (r, c) = match seam.side {
+            0 => (along, n - 1),
+            1 => (n - 1, n - 1 - along),
+            2 => (n - 1 - along, 0),
+            _ => (0, along),
         };
-        if !current_edges.contains(&new) {
-            current_edges.push(new);
+        State {
+            row: seam.face.1 as usize * n + r,
+            col: seam.face.0 as usize * n + c,
+            direction: (seam.side + 2) % 4,
         }
     }
-    graph
+}
+
+// Works out the face size and layout of the net from the board and folds it,
+// returning the folded graph with the face size
+fn fold_board(board: &Board) -> Result<(Graph, usize), NetError> {
+    let face_size = face_size(board)?;
+    let faces = find_faces(board, face_size)?;
+    let graph = Graph::from_faces(&faces).fold();
+    // Every edge around the outside of a net is glued to exactly one other, and
+    // the corners of the net come together as the eight corners of the cube
+    let boundary = graph.boundary_edges();
+    let fully_glued = boundary.iter().all(|edge| graph.equivalent_edges.iter()
+        .filter(|pair| pair.edge_1 == *edge || pair.edge_2 == *edge)
+        .count() == 1);
+    let mut corners: Vec<usize> = graph.node_classes().into_values().collect();
+    corners.sort();
+    corners.dedup();
+    if !fully_glued || corners.len() != 8 {
+        return Err(NetError::DoesNotFold(faces));
+    }
+    Ok((graph, face_size))
+}
+
+#[derive(Debug, PartialEq)]
+enum NetError {
+    // A cube's surface is six square faces, so this many tiles can't be one
+    TileCount(usize),
+    // A face sized square of the board, top left tile given, is only partly on the board
+    PartialFace { row: usize, col: usize },
+    // Some faces aren't joined to the rest by an edge
+    Disconnected(Vec<(u32, u32)>),
+    // The faces are connected but overlap when folded, locations in units of faces
+    DoesNotFold(Vec<(u32, u32)>),
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetError::TileCount(count) => {
+                write!(f, "Board has {} tiles which isn't six square faces", count)
+            }
+            NetError::PartialFace { row, col } => {
+                write!(f, "Face starting at row {} column {} is only partly on the board", row + 1, col + 1)
+            }
+            NetError::Disconnected(faces) => write!(f, "Faces {:?} aren't all joined edge to edge", faces),
+            NetError::DoesNotFold(faces) => write!(f, "Faces {:?} don't fold into a cube", faces),
+        }
+    }
+}
+
+impl std::error::Error for NetError {}
+
+// Each of the six faces has face_size squared tiles
+fn face_size(board: &Board) -> Result<usize, NetError> {
+    let no_tiles = board.tiles.iter().flatten().filter(|tile| **tile != Tile::Empty).count();
+    let face_size = ((no_tiles / 6) as f64).sqrt().round() as usize;
+    if face_size == 0 || 6 * face_size * face_size != no_tiles {
+        return Err(NetError::TileCount(no_tiles));
+    }
+    Ok(face_size)
+}
+
+// Top left corners of the faces in units of faces. The board is split into face
+// sized squares which must each be entirely on or entirely off the board, and as
+// the tile count was checked there are six on it.
+fn find_faces(board: &Board, face_size: usize) -> Result<Vec<(u32, u32)>, NetError> {
+    let mut faces = vec![];
+    for y in 0..board.no_rows.div_ceil(face_size) {
+        for x in 0..board.no_cols.div_ceil(face_size) {
+            let (row, col) = (y * face_size, x * face_size);
+            let no_tiles = (row..row + face_size)
+                .flat_map(|r| (col..col + face_size).map(move |c| (r, c)))
+                .filter(|&(r, c)| *get_tile(board, r, c) != Tile::Empty)
+                .count();
+            if no_tiles == face_size * face_size {
+                faces.push((x as u32, y as u32));
+            } else if no_tiles > 0 {
+                return Err(NetError::PartialFace { row, col });
+            }
+        }
+    }
+
+    let mut connected = vec![faces[0]];
+    let mut to_visit = vec![faces[0]];
+    while let Some((x, y)) = to_visit.pop() {
+        for face in faces.iter() {
+            if x.abs_diff(face.0) + y.abs_diff(face.1) == 1 && !connected.contains(face) {
+                connected.push(*face);
+                to_visit.push(*face);
+            }
+        }
+    }
+    if connected.len() != faces.len() {
+        return Err(NetError::Disconnected(faces));
+    }
+    Ok(faces)
+}
+
+type Point = [f64; 3];
+
+fn add(a: Point, b: Point) -> Point {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: Point, by: f64) -> Point {
+    [a[0] * by, a[1] * by, a[2] * by]
+}
+
+fn dot(a: Point, b: Point) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: Point, b: Point) -> Point {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+// Where each corner of the cube, by node class, sits on the unit cube. The first
+// face of the net lies on z = 0 with x to the right and y down as on the board,
+// and the rest of the net folds away behind it so the board is on the outside.
+fn corner_positions(graph: &Graph) -> HashMap<usize, [i32; 3]> {
+    let classes = graph.node_classes();
+    let first = &graph.faces()[0];
+    let on_first: HashMap<usize, [i32; 3]> = first.iter()
+        .zip([[0, 0, 0], [1, 0, 0], [1, 1, 0], [0, 1, 0]])
+        .map(|(node, position)| (classes[&node.id], position))
+        .collect();
+    // Edges of the net join corners that are next to each other on the cube, and
+    // each corner of the first face has one neighbour that's not on it
+    let mut positions = on_first.clone();
+    for edge in graph.graph.values().flatten() {
+        let (a, b) = (classes[&edge.a.id], classes[&edge.b.id]);
+        for (from, to) in [(a, b), (b, a)] {
+            if let (Some(&[x, y, _]), false) = (on_first.get(&from), on_first.contains_key(&to)) {
+                positions.insert(to, [x, y, 1]);
+            }
+        }
+    }
+    positions
+}
+
+// Corners of each face clockwise from the top left of the face in the net
+fn face_corners(graph: &Graph) -> HashMap<(u32, u32), [Point; 4]> {
+    let classes = graph.node_classes();
+    let positions = corner_positions(graph);
+    graph.faces()
+        .into_iter()
+        .map(|face| (face[0].loc, face.map(|node| positions[&classes[&node.id]].map(f64::from))))
+        .collect()
+}
+
+// A point on a face given in tiles across and down from the face's top left corner
+fn face_point(corners: &[Point; 4], face_size: usize, across: f64, down: f64) -> Point {
+    let n = face_size as f64;
+    let right = scale(sub(corners[1], corners[0]), across / n);
+    let below = scale(sub(corners[3], corners[0]), down / n);
+    add(corners[0], add(right, below))
+}
+
+// An isometric view looking at the centre of the cube from the towards direction
+struct View {
+    towards: Point,
+    right: Point,
+    up: Point,
+}
+
+impl View {
+    fn new(towards: Point, up: Point) -> Self {
+        let towards = scale(towards, 1.0 / dot(towards, towards).sqrt());
+        let right = cross(up, towards);
+        let right = scale(right, 1.0 / dot(right, right).sqrt());
+        View { towards, right, up: cross(towards, right) }
+    }
+
+    fn can_see(&self, corners: &[Point; 4]) -> bool {
+        let centre = scale(add(corners[0], corners[2]), 0.5);
+        dot(sub(centre, [0.5; 3]), self.towards) > 0.0
+    }
+
+    // Position in the drawing with the cube's centre at the origin
+    fn project(&self, point: Point, size: f64) -> (f64, f64) {
+        let point = sub(point, [0.5; 3]);
+        (dot(point, self.right) * size, -dot(point, self.up) * size)
+    }
+
+    fn points(&self, points: &[Point], size: f64) -> String {
+        points.iter()
+            .map(|&point| self.project(point, size))
+            .map(|(x, y)| format!("{:.1},{:.1}", x, y))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+const SVG_CUBE_SIZE: f64 = 200.0;
+
+// Draws the folded cube from opposite corners so all six faces are shown, each
+// labelled with its number in the net counting along the rows. The path is drawn
+// over the walls, with an arrow for the final position and facing.
+fn render_cube_svg(board: &Board, graph: &Graph, face_size: usize, path: &[State]) -> String {
+    let faces = face_corners(graph);
+    let labels: HashMap<(u32, u32), usize> = graph.faces().iter()
+        .enumerate()
+        .map(|(index, face)| (face[0].loc, index + 1))
+        .collect();
+    let corners_of = |state: &State| &faces[&((state.col / face_size) as u32, (state.row / face_size) as u32)];
+    let centre_of = |state: &State| {
+        let (down, across) = (state.row % face_size, state.col % face_size);
+        face_point(corners_of(state), face_size, across as f64 + 0.5, down as f64 + 0.5)
+    };
+    let size = SVG_CUBE_SIZE;
+    let half_width = size * 0.9;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\">\n",
+        4.0 * half_width + 60.0, 2.0 * half_width + 40.0);
+    svg.push_str("<defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"5\" refY=\"5\" \
+        markerWidth=\"5\" markerHeight=\"5\" orient=\"auto\">\
+        <path d=\"M 0 0 L 10 5 L 0 10 z\" fill=\"#d22\"/></marker></defs>\n");
+    let views = [View::new([-1.0, -1.0, -1.0], [0.0, 0.0, -1.0]), View::new([1.0, 1.0, 1.0], [0.0, 0.0, 1.0])];
+    for (index, view) in views.iter().enumerate() {
+        let (x, y) = (20.0 + half_width + index as f64 * (2.0 * half_width + 20.0), 20.0 + half_width);
+        svg.push_str(&format!("<g transform=\"translate({:.1},{:.1})\">\n", x, y));
+        let mut visible: Vec<(&(u32, u32), &[Point; 4])> = faces.iter()
+            .filter(|(_, corners)| view.can_see(corners))
+            .collect();
+        visible.sort_by_key(|(loc, _)| labels[loc]);
+
+        for &(loc, corners) in visible.iter() {
+            svg.push_str(&format!("<polygon points=\"{}\" fill=\"#eee\" stroke=\"#333\"/>\n", view.points(corners, size)));
+            for down in 0..face_size {
+                for across in 0..face_size {
+                    let (row, col) = (loc.1 as usize * face_size + down, loc.0 as usize * face_size + across);
+                    if *get_tile(board, row, col) == Tile::Solid {
+                        let tile = [(0, 0), (1, 0), (1, 1), (0, 1)]
+                            .map(|(x, y)| face_point(corners, face_size, (across + x) as f64, (down + y) as f64));
+                        svg.push_str(&format!("<polygon points=\"{}\" fill=\"#777\"/>\n", view.points(&tile, size)));
+                    }
+                }
+            }
+            let middle = face_size as f64 / 2.0;
+            let (x, y) = view.project(face_point(corners, face_size, middle, middle), size);
+            svg.push_str(&format!(
+                "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"{:.0}\" text-anchor=\"middle\" \
+                dominant-baseline=\"central\" fill=\"#36c\" opacity=\"0.5\">{}</text>\n",
+                x, y, size / 4.0, labels[loc]));
+        }
+
+        // Only the parts of the path on faces we can see, split where it goes out of view
+        let mut runs: Vec<Vec<Point>> = vec![vec![]];
+        for state in path {
+            if view.can_see(corners_of(state)) {
+                runs.last_mut().unwrap().push(centre_of(state));
+            } else if !runs.last().unwrap().is_empty() {
+                runs.push(vec![]);
+            }
+        }
+        for run in runs.iter().filter(|run| run.len() > 1) {
+            svg.push_str(&format!(
+                "<polyline points=\"{}\" fill=\"none\" stroke=\"#e80\" stroke-width=\"2\"/>\n",
+                view.points(run, size)));
+        }
+
+        if let Some(last) = path.last().filter(|last| view.can_see(corners_of(last))) {
+            let corners = corners_of(last);
+            let heading = match last.direction {
+                0 => sub(corners[1], corners[0]),
+                1 => sub(corners[3], corners[0]),
+                2 => sub(corners[0], corners[1]),
+                _ => sub(corners[0], corners[3]),
+            };
+            let from = centre_of(last);
+            let to = add(from, scale(heading, (1.0 / face_size as f64).max(0.1)));
+            let ((x1, y1), (x2, y2)) = (view.project(from, size), view.project(to, size));
+            svg.push_str(&format!("<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"4\" fill=\"#d22\"/>\n", x1, y1));
+            svg.push_str(&format!(
+                "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#d22\" \
+                stroke-width=\"2\" marker-end=\"url(#arrow)\"/>\n",
+                x1, y1, x2, y2));
+        }
+        svg.push_str("</g>\n");
+    }
+    svg.push_str("</svg>\n");
+    svg
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use aoc2022_rust::random::Lcg;
 
     #[test]
     fn it_works() {
         let input = problem::load_raw_test(22);
-        let solution_1 = solve1(&input);
+        let (board, instructions) = parse_inputs(&input).unwrap();
+        let solution_1 = solve1(&board, &instructions);
         assert_eq!(solution_1, 6032);
-//         let solution_2 = solve2(&input);
-//         assert_eq!(solution_2, 70);
+        let solution_2 = solve2(&board, &instructions);
+        assert_eq!(solution_2, Ok(5031));
+    }
+
+    #[test]
+    fn can_parse_inputs() {
+        let (board, instructions) = parse_inputs("  ..#\n .\n\n10R5L0\n").unwrap();
+        assert_eq!((board.no_rows, board.no_cols, board.start_col), (2, 5, 2));
+        assert_eq!(board.tiles[1], vec![Tile::Empty, Tile::Open, Tile::Empty, Tile::Empty, Tile::Empty]);
+        assert_eq!(instructions, vec![
+            Instruction::Move(10), Instruction::RotateR, Instruction::Move(5), Instruction::RotateL, Instruction::Move(0)]);
+        // Windows line endings and trailing spaces are fine
+        assert!(parse_inputs("  ..#  \r\n .\r\n\r\n10R5\r\n").is_ok());
+
+        assert_eq!(parse_inputs("..\n..\n10R5").err(), Some(ParseError::NoPath));
+        assert_eq!(parse_inputs("..\n..\n\n").err(), Some(ParseError::NoPath));
+        let error = parse_inputs("..\n..\n\n10R5\n\nL2").unwrap_err();
+        assert_eq!(error, ParseError::TrailingInput { line: 6 });
+        assert_eq!(error.to_string(), "Unexpected input on line 6 after the path");
+        assert_eq!(parse_inputs("\n10R5").err(), Some(ParseError::EmptyBoard));
+        assert_eq!(parse_inputs("   \n\n10R5").err(), Some(ParseError::EmptyBoard));
+        assert_eq!(parse_inputs("  ##\n ...\n\n10").err(), Some(ParseError::NoStartTile));
+        assert_eq!(parse_inputs("..\n.x\n\n10").err(), Some(ParseError::InvalidTile { line: 2, column: 2, tile: 'x' }));
+        let error = parse_inputs("..\n..\n\n10R5 F2").unwrap_err();
+        assert_eq!(error, ParseError::InvalidInstruction { column: 5, token: String::from(" ") });
+        let error = parse_inputs("..\n..\n\n10R5F2").unwrap_err();
+        assert_eq!(error.to_string(), "Invalid instruction \"F\" at column 5 of the path");
+        let error = parse_inputs("..\n..\n\n99999999999999999999999").unwrap_err();
+        assert_eq!(error, ParseError::InvalidInstruction { column: 1, token: String::from("99999999999999999999999") });
+
+        let (board, _) = parse_inputs(".
+.#
+
+10").unwrap();
+        assert_eq!(board.tiles, vec![vec![Tile::Open, Tile::Empty], vec![Tile::Open, Tile::Solid]]);
     }
 
     #[test]
@@ -438,75 +1090,34 @@ mod tests {
         //       |     |
         //       M_____N
 
-        let a = Node { id: String::from("a"), loc: (1, 0) };
-        let b = Node { id: String::from("b"), loc: (2, 0) };
-        let c = Node { id: String::from("c"), loc: (0, 1) };
-        let d = Node { id: String::from("d"), loc: (1, 1) };
-        let e = Node { id: String::from("e"), loc: (2, 1) };
-        let f = Node { id: String::from("f"), loc: (3, 1) };
-        let g = Node { id: String::from("g"), loc: (0, 2) };
-        let h = Node { id: String::from("h"), loc: (1, 2) };
-        let i = Node { id: String::from("i"), loc: (2, 2) };
-        let j = Node { id: String::from("j"), loc: (3, 2) };
-        let k = Node { id: String::from("k"), loc: (1, 3) };
-        let l = Node { id: String::from("l"), loc: (2, 3) };
-        let m = Node { id: String::from("m"), loc: (1, 4) };
-        let n = Node { id: String::from("n"), loc: (2, 4) };
-
-        let ab = Edge { a: a.clone(), b: b.clone() };
-        let ad = Edge { a: a.clone(), b: d.clone() };
-        let be = Edge { a: b.clone(), b: e.clone() };
-        let cd = Edge { a: c.clone(), b: d.clone() };
-        let de = Edge { a: d.clone(), b: e.clone() };
-        let ef = Edge { a: e.clone(), b: f.clone() };
-        let cg = Edge { a: c.clone(), b: g.clone() };
-        let dh = Edge { a: d.clone(), b: h.clone() };
-        let ei = Edge { a: e.clone(), b: i.clone() };
-        let fj = Edge { a: f.clone(), b: j.clone() };
-        let gh = Edge { a: g.clone(), b: h.clone() };
-        let hi = Edge { a: h.clone(), b: i.clone() };
-        let hk = Edge { a: h.clone(), b: k.clone() };
-        let ij = Edge { a: i.clone(), b: j.clone() };
-        let il = Edge { a: i.clone(), b: l.clone() };
-        let km = Edge { a: k.clone(), b: m.clone() };
-        let kl = Edge { a: k.clone(), b: l.clone() };
-        let ln = Edge { a: l.clone(), b: n.clone() };
-        let mn = Edge { a: m.clone(), b: n.clone() };
-
-        let full_graph = HashMap::from([
-            ("a", vec![ab.clone(), ad.clone()]),
-            ("b", vec![ab.clone(), be.clone()]),
-            ("c", vec![cg.clone(), cd.clone()]),
-            ("d", vec![ad.clone(), cd.clone(), de.clone(), dh.clone()]),
-            ("e", vec![be.clone(), de.clone(), ef.clone(), ei.clone()]),
-            ("f", vec![ef.clone(), fj.clone()]),
-            ("g", vec![cg.clone(), gh.clone()]),
-            ("h", vec![dh.clone(), gh.clone(), hi.clone(), hk.clone()]),
-            ("i", vec![ei.clone(), hi.clone(), ij.clone(), il.clone()]),
-            ("j", vec![fj.clone(), ij.clone()]),
-            ("k", vec![hk.clone(), kl.clone(), km.clone()]),
-            ("l", vec![il.clone(), kl.clone(), ln.clone()]),
-            ("m", vec![km.clone(), mn.clone()]),
-            ("n", vec![ln.clone(), mn.clone()]),
-        ]);
+        let graph = Graph::from_faces(&[(1, 0), (0, 1), (1, 1), (2, 1), (1, 2), (1, 3)]);
+        let nodes = graph.nodes();
+        let [a, b, c, d, e, f, g, h, i, j, k, l, m, n] = [
+            (1, 0), (2, 0), (0, 1), (1, 1), (2, 1), (3, 1), (0, 2),
+            (1, 2), (2, 2), (3, 2), (1, 3), (2, 3), (1, 4), (2, 4)].map(|loc| nodes[&loc].clone());
+        assert_eq!(nodes.len(), 14);
 
-        let graph = Graph {
-            graph: full_graph,
-            equivalent_edges: HashSet::new(),
-            equivalent_nodes: HashSet::new(),
-        };
+        let edge = |x: &Node, y: &Node| Edge { a: x.clone(), b: y.clone() };
+        let (ab, ad, be, cd, ef) = (edge(&a, &b), edge(&a, &d), edge(&b, &e), edge(&c, &d), edge(&e, &f));
+        let (cg, fj, gh, hk, ij) = (edge(&c, &g), edge(&f, &j), edge(&g, &h), edge(&h, &k), edge(&i, &j));
+        let (il, km, ln, mn) = (edge(&i, &l), edge(&k, &m), edge(&l, &n), edge(&m, &n));
+        assert!(graph.has_edge(&d, &e) && graph.has_edge(&h, &i) && graph.has_edge(&k, &l));
+        assert!(!graph.has_edge(&d, &i));
+        assert_eq!(graph.faces().len(), 6);
+        let boundary: HashSet<Edge> = graph.boundary_edges().into_iter().collect();
+        assert_eq!(boundary, HashSet::from([ab.clone(), ad.clone(), be.clone(), cd.clone(),
+            cg.clone(), ef.clone(), fj.clone(), gh.clone(), hk.clone(), ij.clone(), il.clone(),
+            km.clone(), ln.clone(), mn.clone()]));
 
+        let glued = |x: &Edge, y: &Edge| EquivalentEdges { edge_1: x.clone(), edge_2: y.clone() };
         let folded = graph.fold_graph();
         let expected = HashSet::from([
-            EquivalentEdges { edge_1: &ad, edge_2: &cd },
-            EquivalentEdges { edge_1: &be, edge_2: &ef },
-            EquivalentEdges { edge_1: &gh, edge_2: &hk },
-            EquivalentEdges { edge_1: &ij, edge_2: &il }]);
-        // After first pass
-        assert_eq!(folded.equivalent_edges.len(), expected.len());
-        for set in expected.iter() {
-            assert!(folded.equivalent_edges.contains(set))
-        }
+            glued(&ad, &cd),
+            glued(&be, &ef),
+            glued(&gh, &hk),
+            glued(&ij, &il)]);
+        // After first pass only the corners with three faces in the net are folded
+        assert_eq!(folded.equivalent_edges, expected);
 
         let expected_nodes = HashSet::from([
             EquivalentNode { a: a.clone(), b: c.clone() },
@@ -514,54 +1125,303 @@ mod tests {
             EquivalentNode { a: g.clone(), b: k.clone() },
             EquivalentNode { a: l.clone(), b: j.clone() }
         ]);
-        assert_eq!(folded.equivalent_nodes.len(), expected_nodes.len());
-        assert_eq!(folded.equivalent_edges.len(), expected.len());
-        for node in expected_nodes.iter() {
-            assert!(folded.equivalent_nodes.contains(node))
-        }
+        assert_eq!(folded.equivalent_nodes, expected_nodes);
 
-        // 2nd pass
-        println!("{:?}", folded.graph.get("a").unwrap());
-        println!("{:?}", folded.graph.get("m").unwrap());
-        let folded = folded.fold_graph();
+        // Folding those brings more corners up to three faces until every edge is glued
+        let folded = graph.fold();
         let expected = HashSet::from([
-            EquivalentEdges { edge_1: &ad, edge_2: &cd },
-            EquivalentEdges { edge_1: &be, edge_2: &ef },
-            EquivalentEdges { edge_1: &gh, edge_2: &hk },
-            EquivalentEdges { edge_1: &ij, edge_2: &il },
-            EquivalentEdges { edge_1: &ab, edge_2: &mn },
-            EquivalentEdges { edge_1: &km, edge_2: &cg },
-            EquivalentEdges { edge_1: &ln, edge_2: &fj }]);
+            glued(&ad, &cd),
+            glued(&be, &ef),
+            glued(&gh, &hk),
+            glued(&ij, &il),
+            glued(&ab, &mn),
+            glued(&km, &cg),
+            glued(&ln, &fj)]);
+        assert_eq!(folded.equivalent_edges, expected);
 
-        println!("{:?}", folded.equivalent_nodes);
-        println!("{:?}", folded.equivalent_edges);
-        //assert_eq!(folded.equivalent_edges.len(), expected.len());
-        for set in expected.iter() {
-            assert!(folded.equivalent_edges.contains(set))
+        // A cube has eight corners
+        let classes = folded.node_classes();
+        let mut corners: Vec<usize> = classes.values().copied().collect();
+        corners.sort();
+        corners.dedup();
+        assert_eq!(corners.len(), 8);
+        for (x, y) in [(&a, &c), (&a, &m), (&b, &f), (&b, &n), (&g, &k), (&j, &l)] {
+            assert_eq!(classes[&x.id], classes[&y.id]);
         }
+        assert_ne!(classes[&a.id], classes[&b.id]);
+    }
 
-        let expected_nodes = HashSet::from([
-            EquivalentNode { a: a.clone(), b: c.clone() },
-            EquivalentNode { a: b.clone(), b: f.clone() },
-            EquivalentNode { a: g.clone(), b: k.clone() },
-            EquivalentNode { a: l.clone(), b: j.clone() },
-            EquivalentNode { a: a.clone(), b: m.clone() },
-            EquivalentNode { a: b.clone(), b: n.clone() },
-            EquivalentNode { a: c.clone(), b: m.clone() },
-            EquivalentNode { a: f.clone(), b: n.clone() },
-        ]);
-        assert_eq!(folded.equivalent_nodes.len(), expected_nodes.len());
-        assert_eq!(folded.equivalent_edges.len(), expected.len());
-        for node in expected_nodes.iter() {
-            assert!(folded.equivalent_nodes.contains(node))
+    #[test]
+    fn can_walk_over_cube_edges() {
+        let input = problem::load_raw_test(22);
+        let (board, _) = parse_inputs(&input).unwrap();
+        assert_eq!(face_size(&board), Ok(4));
+        assert_eq!(find_faces(&board, 4), Ok(vec![(2, 0), (0, 1), (1, 1), (2, 1), (2, 2), (3, 2)]));
+        let cube = Cube::from_board(&board).unwrap();
+        assert_eq!(cube.face_size, 4);
+        assert_eq!(cube.seams.len(), 14);
+
+        // The examples from the puzzle, A to B walking right off face 4 onto face 6
+        let state = cube.move_one(&State { row: 5, col: 11, direction: 0 }, &board);
+        assert_eq!(state, State { row: 8, col: 14, direction: 1 });
+        // C to D walking down off face 5 onto face 2
+        let state = cube.move_one(&State { row: 11, col: 10, direction: 1 }, &board);
+        assert_eq!(state, State { row: 7, col: 1, direction: 3 });
+        // Walking back the way we came undoes the move
+        let state = cube.move_one(&State { row: 7, col: 1, direction: 1 }, &board);
+        assert_eq!(state, State { row: 11, col: 10, direction: 3 });
+        // Moves within the net don't need a seam
+        let state = cube.move_one(&State { row: 4, col: 3, direction: 0 }, &board);
+        assert_eq!(state, State { row: 4, col: 4, direction: 0 });
+    }
+
+    #[test]
+    fn can_render_path() {
+        let input = problem::load_raw_test(22);
+        let (board, instructions) = parse_inputs(&input).unwrap();
+        let path = walk_path(&board, &instructions, &Jumps::new(&board, &Wrap::Flat));
+        assert_eq!(path.last().unwrap().get_final_password(), 6032);
+        let expected = "        >>v#
+        .#v.
+        #.v.
+        ..v.
+...#...v..v#
+>>>v...>#.>>
+..#v...#....
+...>>>>v..#.
+        ...#....
+        .....#..
+        .#......
+        ......#.";
+        assert_eq!(render_path(&board, &path, false), expected);
+
+        let (board, instructions) = parse_inputs(&input).unwrap();
+        let wrap = Wrap::Cube(Cube::from_board(&board).unwrap());
+        let path = walk_path(&board, &instructions, &Jumps::new(&board, &wrap));
+        let expected = "        >>v#
+        .#v.
+        #.v.
+        ..v.
+...#..^...v#
+.>>>>>^.#.>>
+.^#....#....
+.^........#.
+        ...#..v.
+        .....#v.
+        .#v<<<<.
+        ..v...#.";
+        assert_eq!(render_path(&board, &path, false), expected);
+        let coloured = render_path(&board, &path, true);
+        assert!(coloured.starts_with("        \x1b[33m>\x1b[0m\x1b[33m>\x1b[0m\x1b[33mv\x1b[0m\x1b[90m#\x1b[0m\n"));
+        assert!(coloured.contains("\x1b[1;31m^\x1b[0m"));
+    }
+
+    #[test]
+    fn can_place_faces_on_cube() {
+        let input = problem::load_raw_test(22);
+        let (board, instructions) = parse_inputs(&input).unwrap();
+        let (graph, face_size) = fold_board(&board).unwrap();
+        let positions: HashSet<[i32; 3]> = corner_positions(&graph).into_values().collect();
+        assert_eq!(positions.len(), 8);
+        assert!(positions.iter().flatten().all(|&x| x == 0 || x == 1));
+
+        // Each face is flat against a different side of the cube
+        let faces = face_corners(&graph);
+        let sides: HashSet<(usize, i32)> = faces.values()
+            .map(|corners| {
+                let axis = (0..3).find(|&axis| corners.iter().all(|corner| corner[axis] == corners[0][axis])).unwrap();
+                (axis, corners[0][axis] as i32)
+            })
+            .collect();
+        assert_eq!(sides.len(), 6);
+        // Face 1 is on top, face 4 is folded down below it and face 5 below that is opposite face 1
+        assert!(faces[&(2, 0)].iter().all(|corner| corner[2] == 0.0));
+        assert!(faces[&(2, 1)].iter().all(|corner| corner[1] == 1.0));
+        assert!(faces[&(2, 2)].iter().all(|corner| corner[2] == 1.0));
+
+        let jumps = Jumps::new(&board, &Wrap::Cube(Cube::new(&graph, face_size)));
+        let path = walk_path(&board, &instructions, &jumps);
+        let svg = render_cube_svg(&board, &graph, face_size, &path);
+        // Each face is drawn in one of the two views
+        for label in 1..=6 {
+            assert_eq!(svg.matches(&format!(">{}</text>", label)).count(), 1);
+        }
+        assert_eq!(svg.matches("marker-end").count(), 1);
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+    }
+
+    // Draws faces at the given locations as a board of open tiles
+    fn net_board(faces: &[(u32, u32)], face_size: usize) -> Board {
+        let width = faces.iter().map(|&(x, _)| x as usize + 1).max().unwrap() * face_size;
+        let height = faces.iter().map(|&(_, y)| y as usize + 1).max().unwrap() * face_size;
+        let mut input = String::new();
+        for row in 0..height {
+            let line: String = (0..width)
+                .map(|col| (col / face_size) as u32)
+                .map(|x| if faces.contains(&(x, (row / face_size) as u32)) { '.' } else { ' ' })
+                .collect();
+            input.push_str(line.trim_end());
+            input.push('\n');
+        }
+        input.push_str("\n10R5");
+        parse_inputs(&input).unwrap().0
+    }
+
+    // Every shape made of six squares joined edge to edge, one of each up to
+    // rotation and reflection, with locations starting from (0, 0)
+    fn hexominoes() -> Vec<Vec<(u32, u32)>> {
+        let normalise = |cells: &[(i32, i32)]| {
+            let (min_x, min_y) = (cells.iter().map(|c| c.0).min().unwrap(), cells.iter().map(|c| c.1).min().unwrap());
+            let mut cells: Vec<(i32, i32)> = cells.iter().map(|&(x, y)| (x - min_x, y - min_y)).collect();
+            cells.sort();
+            cells
+        };
+        let canonical = |cells: &[(i32, i32)]| {
+            (0..8)
+                .map(|symmetry| {
+                    let transformed: Vec<(i32, i32)> = cells.iter()
+                        .map(|&(x, y)| if symmetry & 4 == 0 { (x, y) } else { (y, x) })
+                        .map(|(x, y)| (if symmetry & 1 == 0 { x } else { -x }, if symmetry & 2 == 0 { y } else { -y }))
+                        .collect();
+                    normalise(&transformed)
+                })
+                .min()
+                .unwrap()
+        };
+        let mut shapes: HashSet<Vec<(i32, i32)>> = HashSet::from([vec![(0, 0)]]);
+        for _ in 1..6 {
+            shapes = shapes.iter()
+                .flat_map(|shape| shape.iter()
+                    .flat_map(|&(x, y)| [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)])
+                    .filter(|cell| !shape.contains(cell))
+                    .map(|cell| canonical(&[shape.clone(), vec![cell]].concat()))
+                    .collect::<Vec<_>>())
+                .collect();
+        }
+        let mut shapes: Vec<Vec<(u32, u32)>> = shapes.into_iter()
+            .map(|shape| shape.into_iter().map(|(x, y)| (x as u32, y as u32)).collect())
+            .collect();
+        shapes.sort();
+        shapes
+    }
+
+    #[test]
+    fn can_detect_every_cube_net() {
+        let shapes = hexominoes();
+        assert_eq!(shapes.len(), 35);
+        let mut no_nets = 0;
+        for shape in shapes {
+            for face_size in [1, 3] {
+                let board = net_board(&shape, face_size);
+                match Cube::from_board(&board) {
+                    Ok(cube) => {
+                        assert_eq!(cube.face_size, face_size);
+                        assert_eq!(cube.seams.len(), 14);
+                        no_nets += 1;
+                    }
+                    Err(error) => assert!(matches!(error, NetError::DoesNotFold(_)), "{:?} {}", shape, error),
+                }
+            }
+        }
+        // Only 11 of the hexominoes fold into a cube
+        assert_eq!(no_nets, 2 * 11);
+    }
+
+    #[test]
+    fn rejects_invalid_nets() {
+        // A strip of six faces wraps round on itself
+        let strip: Vec<(u32, u32)> = (0..6).map(|x| (x, 0)).collect();
+        let error = Cube::from_board(&net_board(&strip, 2)).err().unwrap();
+        assert_eq!(error, NetError::DoesNotFold(strip));
+
+        // Corners touching isn't enough to join faces
+        let faces = [(0, 0), (1, 0), (2, 0), (3, 1), (4, 1), (5, 1)];
+        let error = Cube::from_board(&net_board(&faces, 2)).err().unwrap();
+        assert_eq!(error, NetError::Disconnected(faces.to_vec()));
+        assert_eq!(error.to_string(), "Faces [(0, 0), (1, 0), (2, 0), (3, 1), (4, 1), (5, 1)] aren't all joined edge to edge");
+
+        let (board, _) = parse_inputs("....\n...\n\n10").unwrap();
+        assert_eq!(face_size(&board), Err(NetError::TileCount(7)));
+        let (board, _) = parse_inputs("..\n..\n\n10").unwrap();
+        assert_eq!(Cube::from_board(&board).err(), Some(NetError::TileCount(4)));
+
+        // The right amount of tiles but not lined up as faces
+        let board = net_board(&[(1, 0), (0, 1), (1, 1), (2, 1), (1, 2), (1, 3)], 2);
+        let tiles = board.tiles.into_iter()
+            .map(|row| [vec![Tile::Empty], row].into_iter().flatten().collect())
+            .collect();
+        let shifted = Board::new(tiles).unwrap();
+        assert_eq!(Cube::from_board(&shifted).err(), Some(NetError::PartialFace { row: 0, col: 2 }));
+    }
+
+    // Walks one tile at a time without the jump table, how moves were made before it
+    fn walk(state: State, steps: usize, board: &Board, wrap: &Wrap) -> State {
+        iterate(state, |state| match wrap {
+            Wrap::Flat => move_one(state, board),
+            Wrap::Cube(cube) => cube.move_one(state, board),
+        })
+            .filter(|s| *get_tile(board, s.row, s.col) != Tile::Empty)
+            .take(steps + 1)
+            .take_while(|s| board.tiles[s.row][s.col] == Tile::Open)
+            .last()
+            .unwrap()
+    }
+
+    fn generate_path(n_instructions: usize, max_steps: u64) -> String {
+        let mut random = Lcg::new(2022);
+        let mut path = String::new();
+        for _ in 0..n_instructions {
+            let seed = random.next_u64();
+            path.push_str(&((seed >> 32) % max_steps + 1).to_string());
+            path.push(if seed >> 63 == 0 { 'L' } else { 'R' });
+        }
+        path
+    }
+
+    #[test]
+    fn jumps_match_walking() {
+        let input = problem::load_raw_test(22);
+        let (board, _) = parse_inputs(&input).unwrap();
+        for wrap in [Wrap::Flat, Wrap::Cube(Cube::from_board(&board).unwrap())] {
+            let jumps = Jumps::new(&board, &wrap);
+            for (row, tiles) in board.tiles.iter().enumerate() {
+                for (col, _) in tiles.iter().enumerate().filter(|(_, tile)| **tile == Tile::Open) {
+                    for direction in 0..4 {
+                        let state = State { row, col, direction };
+                        for steps in [1, 5, 20] {
+                            assert_eq!(
+                                run_instruction(state, Instruction::Move(steps), &jumps),
+                                walk(state, steps, &board, &wrap));
+                        }
+                    }
+                }
+            }
         }
+    }
 
-        //
-        // graph.find_equivalent_edges();
-        // // After 2nd pass
-        // assert_eq!(graph.duplicate_edges,
-        //            vec![(ad.clone(), cd.clone()), (be.clone(), ef.clone()), (gh.clone(), hk.clone()), (ij.clone(), il.clone()),
-        //                 (ab.clone(), mn.clone()), (km.clone(), cg.clone()), (ln.clone(), fj.clone())]
-        // );
+    #[test]
+    #[ignore]
+    fn bench_jumps_against_walking() {
+        let input = problem::load_raw_test(22);
+        let (board, _) = parse_inputs(&input).unwrap();
+        let path = generate_path(1_000_000, 50);
+        for wrap in [Wrap::Flat, Wrap::Cube(Cube::from_board(&board).unwrap())] {
+            let (walk_instructions, jump_instructions) = (parse_instructions(&path).unwrap(), parse_instructions(&path).unwrap());
+            let start = std::time::Instant::now();
+            let walked = walk_instructions.into_iter().fold(State::new(&board), |state, instruction| match instruction {
+                Instruction::Move(steps) => walk(state, steps, &board, &wrap),
+                turn => run_instruction(state, turn, &Jumps { no_cols: 0, next: vec![] }),
+            });
+            let walk_time = start.elapsed();
+
+            let start = std::time::Instant::now();
+            let jumps = Jumps::new(&board, &wrap);
+            let jumped = jump_instructions.into_iter()
+                .fold(State::new(&board), |state, instruction| run_instruction(state, instruction, &jumps));
+            let jump_time = start.elapsed();
+
+            println!("Walking {:?}, jumps {:?}", walk_time, jump_time);
+            assert_eq!(walked, jumped);
+        }
     }
-}
\ No newline at end of file
+}
@@ -1,75 +1,271 @@
 use aoc2022_rust::problem;
-use std::collections::HashSet;
+use clap::Parser;
+use std::fmt;
+
+/// Find the items packed in the wrong compartment and each group's badge
+#[derive(Parser, Debug)]
+struct Cli {
+    /// Number of elves in each group sharing a badge
+    #[arg(short, long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    group: u32,
+}
 
 fn main() {
+    let args = Cli::parse();
     let input = problem::load(3);
-    let solution_1 = solve1(&input);
+    let (solution_1, diagnostics_1) = solve1(&input);
     println!("Puzzle 1 {}", solution_1);
-    let solution_2 = solve2(&input);
+    let (solution_2, diagnostics_2) = solve2(&input, args.group as usize);
     println!("Puzzle 2 {}", solution_2);
+    for diagnostic in diagnostics_1.iter().chain(diagnostics_2.iter()) {
+        eprintln!("{}", diagnostic);
+    }
 }
 
-fn solve1(input: &str) -> u32 {
-    return input
-        .lines()
-        .map(|x| get_priority(get_common_item(x.trim()))).sum();
+#[derive(Debug, PartialEq)]
+enum Problem {
+    // Compartments are the two halves so must be the same size
+    OddLength(usize),
+    InvalidItems(String),
+    NoCommonItem,
+    SeveralCommonItems(String),
+    // The last group is short of elves, the number it has
+    IncompleteGroup(usize),
 }
 
-fn get_common_item(backpack: &str) -> char {
-    let size = backpack.chars().count();
-    let mut iter = backpack.chars();
-    let set: HashSet<char> = (&mut iter).take(size/2).collect();
-    for item in iter {
-        if set.contains(&item) {
-            return item;
+// Something wrong with a rucksack or a group of them, the entry is left out of the sum
+#[derive(Debug, PartialEq)]
+struct Diagnostic {
+    // Lines are numbered from 1, a group spans several
+    first_line: usize,
+    last_line: usize,
+    problem: Problem,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.first_line == self.last_line {
+            write!(f, "Line {}: ", self.first_line)?;
+        } else {
+            write!(f, "Lines {}-{}: ", self.first_line, self.last_line)?;
+        }
+        match &self.problem {
+            Problem::OddLength(length) => write!(f, "odd number of items {} can't be split in two", length),
+            Problem::InvalidItems(items) => write!(f, "invalid items {:?}", items),
+            Problem::NoCommonItem => write!(f, "no item in common"),
+            Problem::SeveralCommonItems(items) => write!(f, "several items in common {:?}", items),
+            Problem::IncompleteGroup(size) => write!(f, "incomplete group of {}", size),
         }
     }
-    panic!("No common item in backpack");
 }
 
-fn get_priority(item: char) -> u32 {
-    // We want a-z -> 1 - 26 and A-Z -> 27-52
-    return if item.is_lowercase() {
-        item as u32 - 96
-    } else {
-        item as u32 - 38
+// A set of items with a bit for each item type, a-z are bits 0 to 25 and A-Z
+// are bits 26 to 51, so an item's priority is one more than its bit
+type Items = u64;
+
+fn item_bit(item: char) -> Items {
+    match item {
+        'a'..='z' => 1 << (item as u32 - 'a' as u32),
+        'A'..='Z' => 1 << (item as u32 - 'A' as u32 + 26),
+        _ => 0,
     }
 }
 
-fn solve2(input: &str) -> u32 {
-    let mut lines = input.lines().peekable();
-    let mut sum: u32 = 0;
-    // Relying on that fact that if we can get 1 we can get 3 as this is a nice puzzle
-    while lines.peek().is_some() {
-        sum += get_priority(get_common_items(lines.next().unwrap(),
-                                             lines.next().unwrap(),
-                                             lines.next().unwrap()));
+fn to_items(items: &str) -> Items {
+    items.chars().fold(0, |set, item| set | item_bit(item))
+}
+
+// Priority of the lowest priority item in the set
+fn get_priority(items: Items) -> Option<u32> {
+    (items != 0).then(|| items.trailing_zeros() + 1)
+}
+
+fn items_to_string(items: Items) -> String {
+    ('a'..='z').chain('A'..='Z').filter(|&item| items & item_bit(item) != 0).collect()
+}
+
+// The priority of the one common item, or what's wrong if there isn't exactly one
+fn get_single_priority(common: Items) -> Result<u32, Problem> {
+    match common.count_ones() {
+        0 => Err(Problem::NoCommonItem),
+        1 => Ok(get_priority(common).unwrap()),
+        _ => Err(Problem::SeveralCommonItems(items_to_string(common))),
     }
-    return sum;
 }
 
-fn get_common_items(backpack1: &str, backpack2: &str, backpack3: &str) -> char {
-    for item1 in backpack1.chars() {
-        for item2 in backpack2.chars() {
-            for item3 in backpack3.chars() {
-                if item1 == item2 && item2 == item3 {
-                    return item1;
-                }
-            }
+fn check_items(backpack: &str) -> Result<(), Problem> {
+    let invalid: String = backpack.chars().filter(|&item| item_bit(item) == 0).collect();
+    if invalid.is_empty() { Ok(()) } else { Err(Problem::InvalidItems(invalid)) }
+}
+
+fn get_common_item(backpack: &str) -> Items {
+    let (first, second) = backpack.split_at(backpack.len() / 2);
+    to_items(first) & to_items(second)
+}
+
+fn get_common_items<'a>(backpacks: impl IntoIterator<Item = &'a str>) -> Items {
+    backpacks.into_iter().fold(Items::MAX, |common, backpack| common & to_items(backpack))
+}
+
+// Sums the priorities of the rucksacks and groups that are fine, along with what's
+// wrong with the rest
+fn sum_priorities(priorities: impl Iterator<Item = Result<u32, Diagnostic>>) -> (u32, Vec<Diagnostic>) {
+    let mut sum = 0;
+    let mut diagnostics = vec![];
+    for priority in priorities {
+        match priority {
+            Ok(priority) => sum += priority,
+            Err(diagnostic) => diagnostics.push(diagnostic),
         }
     }
-    panic!("failed to find common item")
+    (sum, diagnostics)
+}
+
+fn solve1(input: &str) -> (u32, Vec<Diagnostic>) {
+    sum_priorities(input.lines().enumerate().map(|(i, backpack)| {
+        let backpack = backpack.trim();
+        let length = backpack.chars().count();
+        check_items(backpack)
+            .and(if length % 2 == 1 { Err(Problem::OddLength(length)) } else { Ok(()) })
+            .and_then(|_| get_single_priority(get_common_item(backpack)))
+            .map_err(|problem| Diagnostic { first_line: i + 1, last_line: i + 1, problem })
+    }))
+}
+
+fn solve2(input: &str, group_size: usize) -> (u32, Vec<Diagnostic>) {
+    let lines: Vec<&str> = input.lines().map(str::trim).collect();
+    sum_priorities(lines.chunks(group_size).enumerate().map(|(i, group)| {
+        let first_line = i * group_size + 1;
+        let last_line = first_line + group.len() - 1;
+        let complete = if group.len() < group_size { Err(Problem::IncompleteGroup(group.len())) } else { Ok(()) };
+        complete
+            .and_then(|_| group.iter().try_for_each(|backpack| check_items(backpack)))
+            .and_then(|_| get_single_priority(get_common_items(group.iter().copied())))
+            .map_err(|problem| Diagnostic { first_line, last_line, problem })
+    }))
 }
 
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use aoc2022_rust::random::Lcg;
+    use std::collections::HashSet;
+
     #[test]
     fn it_works() {
         let input = problem::load_test(3);
         let solution_1 = solve1(&input);
-        assert_eq!(solution_1, 157);
-        let solution_2 = solve2(&input);
-        assert_eq!(solution_2, 70);
+        assert_eq!(solution_1, (157, vec![]));
+        let solution_2 = solve2(&input, 3);
+        assert_eq!(solution_2, (70, vec![]));
+    }
+
+    #[test]
+    fn reports_problems_with_line_numbers() {
+        let input = "abcb\nabcab\nab-a\nabcd\nabab\n\nxzyz";
+        let (sum, diagnostics) = solve1(input);
+        assert_eq!(sum, 2 + 26);
+        let problems: Vec<String> = diagnostics.iter().map(|diagnostic| diagnostic.to_string()).collect();
+        assert_eq!(problems, vec![
+            "Line 2: odd number of items 5 can't be split in two",
+            "Line 3: invalid items \"-\"",
+            "Line 4: no item in common",
+            "Line 5: several items in common \"ab\"",
+            "Line 6: no item in common",
+        ]);
+
+        let (sum, diagnostics) = solve2(input, 2);
+        assert_eq!(sum, 0);
+        assert_eq!(diagnostics, vec![
+            Diagnostic { first_line: 1, last_line: 2, problem: Problem::SeveralCommonItems(String::from("abc")) },
+            Diagnostic { first_line: 3, last_line: 4, problem: Problem::InvalidItems(String::from("-")) },
+            Diagnostic { first_line: 5, last_line: 6, problem: Problem::NoCommonItem },
+            Diagnostic { first_line: 7, last_line: 7, problem: Problem::IncompleteGroup(1) },
+        ]);
+        assert_eq!(diagnostics[3].to_string(), "Line 7: incomplete group of 1");
+        assert_eq!(solve2("abc\nbcd\ncde\nzZ", 3), (3, vec![
+            Diagnostic { first_line: 4, last_line: 4, problem: Problem::IncompleteGroup(1) }]));
+    }
+
+    #[test]
+    fn can_use_any_group_size() {
+        let input = problem::load_test(3);
+        assert_eq!(to_items("aAzZ"), 1 | 1 << 25 | 1 << 26 | 1 << 51);
+        assert_eq!(get_priority(to_items("Zzp")), Some(16));
+        assert_eq!(get_priority(0), None);
+        // The first three share r and the last three Z, all six only have those two in common
+        assert_eq!(get_common_items(input.lines()), item_bit('r') & item_bit('Z'));
+        assert_eq!(get_common_items(input.lines().take(2)), to_items("rsFMf"));
+        assert_eq!(solve2("abcb\nbdbe", 2), (2, vec![]));
+        // Each elf on their own has a single item
+        assert_eq!(solve2("bb\nZZ", 1), (2 + 52, vec![]));
+    }
+
+    // The original implementations using a set of items and nested loops
+    fn get_common_item_set(backpack: &str) -> char {
+        let size = backpack.chars().count();
+        let mut iter = backpack.chars();
+        let set: HashSet<char> = (&mut iter).take(size / 2).collect();
+        iter.find(|item| set.contains(item)).expect("No common item in backpack")
+    }
+
+    fn get_common_items_loops(backpack1: &str, backpack2: &str, backpack3: &str) -> char {
+        for item1 in backpack1.chars() {
+            for item2 in backpack2.chars() {
+                for item3 in backpack3.chars() {
+                    if item1 == item2 && item2 == item3 {
+                        return item1;
+                    }
+                }
+            }
+        }
+        panic!("failed to find common item")
     }
-}
\ No newline at end of file
+
+    fn get_char_priority(item: char) -> u32 {
+        get_priority(item_bit(item)).unwrap()
+    }
+
+    // Groups of three rucksacks sharing one badge, where each rucksack has one item in
+    // both compartments
+    fn generate_rucksacks(n_groups: usize, half_size: usize) -> String {
+        let letters: Vec<char> = ('a'..='z').chain('A'..='Z').collect();
+        let mut lcg = Lcg::new(2022);
+        let mut random = |n: usize| lcg.below(n as u64) as usize;
+        let mut lines = vec![];
+        for _ in 0..n_groups {
+            let mut pool = letters.clone();
+            let badge = pool.swap_remove(random(pool.len()));
+            // Each rucksack draws from its own 17 letters so only the badge is shared
+            for elf in 0..3 {
+                let own = &pool[elf * 17..(elf + 1) * 17];
+                let (both, first, second) = (own[0], &own[1..9], &own[9..17]);
+                let mut left = vec![both, badge];
+                left.extend((2..half_size).map(|_| first[random(8)]));
+                let mut right = vec![both];
+                right.extend((1..half_size).map(|_| second[random(8)]));
+                lines.push(left.into_iter().chain(right).collect::<String>());
+            }
+        }
+        lines.join("\n")
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_masks_against_sets() {
+        let input = generate_rucksacks(100_000, 20);
+        let lines: Vec<&str> = input.lines().collect();
+
+        let start = std::time::Instant::now();
+        let by_set: u32 = lines.iter().map(|line| get_char_priority(get_common_item_set(line))).sum::<u32>() +
+            lines.chunks(3).map(|group| get_char_priority(get_common_items_loops(group[0], group[1], group[2]))).sum::<u32>();
+        let set_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let by_mask = solve1(&input).0 + solve2(&input, 3).0;
+        let mask_time = start.elapsed();
+
+        println!("Sets and loops {:?}, masks {:?}", set_time, mask_time);
+        assert_eq!(by_set, by_mask);
+    }
+}
@@ -1,28 +1,76 @@
+use aoc2022_rust::interval::{Interval, IntervalSet};
 use aoc2022_rust::problem;
+use clap::Parser;
 use regex::Regex;
 use lazy_static::lazy_static;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+/// Find the elves whose cleaning assignments overlap
+#[derive(Parser, Debug)]
+struct Cli {
+    /// Report sections nobody covers and work duplicated within pairs
+    #[arg(short, long)]
+    coverage: bool,
+    /// Compare every assignment in the file, not only the two in each pair
+    #[arg(short, long)]
+    roster: bool,
+    /// Write the roster's conflict graph to this file in Graphviz dot format
+    #[arg(long)]
+    graph: Option<PathBuf>,
+}
 
 fn main() {
+    let args = Cli::parse();
     let input = problem::load(4);
     let solution_1 = solve1(&input);
     println!("Puzzle 1 {}", solution_1);
     let solution_2 = solve2(&input);
     println!("Puzzle 2 {}", solution_2);
+    let pairs = parse_pairs(&input);
+    if args.coverage {
+        let gaps: Vec<String> = uncovered(&pairs).intervals().iter()
+            .map(|gap| format!("{}-{}", gap.start(), gap.end()))
+            .collect();
+        println!("Sections nobody covers {}", if gaps.is_empty() { String::from("none") } else { gaps.join(", ") });
+        println!("Sections cleaned twice within pairs {}", duplicated(&pairs));
+    }
+    if args.roster || args.graph.is_some() {
+        let elves = roster(&pairs);
+        let conflicts = conflicts(&elves);
+        let containing = conflicts.iter().filter(|conflict| conflict.relation == Relation::Contains).count();
+        println!("Roster conflicts {} ({} containing, {} overlapping)",
+            conflicts.len(), containing, conflicts.len() - containing);
+        let depths = coverage_depth(&elves);
+        if let Some(peak) = depths.iter().map(|&(_, depth)| depth).max() {
+            let sections: Vec<String> = depths.iter()
+                .filter(|&&(_, depth)| depth == peak)
+                .map(|(sections, _)| format!("{}-{}", sections.start(), sections.end()))
+                .collect();
+            println!("Most elves on one section {} at {}", peak, sections.join(", "));
+        }
+        if let Some(file) = &args.graph {
+            fs::write(file, conflict_graph(&elves, &conflicts))
+                .unwrap_or_else(|error| eprintln!("Could not write {}: {}", file.display(), error));
+        }
+    }
 }
 
 struct Pair {
-    elf_1: (u32, u32),
-    elf_2: (u32, u32)
+    elf_1: Interval,
+    elf_2: Interval,
 }
 
 impl Pair {
     fn is_subset(&self) -> bool {
-        (self.elf_1.0 >= self.elf_2.0 && self.elf_1.1 <= self.elf_2.1) || // elf 1 inside elf 2
-            (self.elf_1.0 <= self.elf_2.0 && self.elf_1.1 >= self.elf_2.1)// elf 2 inside elf 1
+        self.elf_1.contains_interval(&self.elf_2) || self.elf_2.contains_interval(&self.elf_1)
     }
 
     fn range_overlaps(&self) -> bool {
-        self.elf_1.1 >= self.elf_2.0 && self.elf_1.0 <= self.elf_2.1
+        self.elf_1.overlaps(&self.elf_2)
     }
 }
 
@@ -33,27 +81,147 @@ fn new_pair(input: &str) -> Pair {
     let matches: Vec<u32> = RE.find_iter(input)
         .filter_map(|digits| digits.as_str().parse().ok())
         .collect();
+    let assignment = |start, end| Interval::new(start, end)
+        .unwrap_or_else(|| panic!("Assignment ends before it starts in {}", input));
     Pair {
-        elf_1: (matches[0], matches[1]),
-        elf_2: (matches[2], matches[3])
+        elf_1: assignment(matches[0], matches[1]),
+        elf_2: assignment(matches[2], matches[3]),
+    }
+}
+
+fn parse_pairs(input: &str) -> Vec<Pair> {
+    input.lines().map(|x| new_pair(x.trim())).collect()
+}
+
+// Sections between the lowest and highest assigned that nobody has been given
+fn uncovered(pairs: &[Pair]) -> IntervalSet {
+    let covered: IntervalSet = pairs.iter().flat_map(|pair| [pair.elf_1, pair.elf_2]).collect();
+    match covered.span() {
+        Some(span) => IntervalSet::from(span).difference(&covered),
+        None => IntervalSet::new(),
+    }
+}
+
+// Total sections that both elves in a pair have been given
+fn duplicated(pairs: &[Pair]) -> u64 {
+    pairs.iter()
+        .filter_map(|pair| pair.elf_1.intersection(&pair.elf_2))
+        .map(|overlap| overlap.size())
+        .sum()
+}
+
+// One elf's assignment, identified by its line and whether it was first or second in the pair
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Elf {
+    // Lines are numbered from 1
+    line: usize,
+    side: usize,
+    sections: Interval,
+}
+
+impl fmt::Display for Elf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.line, self.side)
+    }
+}
+
+fn roster(pairs: &[Pair]) -> Vec<Elf> {
+    pairs.iter().enumerate()
+        .flat_map(|(index, pair)| [
+            Elf { line: index + 1, side: 1, sections: pair.elf_1 },
+            Elf { line: index + 1, side: 2, sections: pair.elf_2 },
+        ])
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Relation {
+    Overlaps,
+    // The first elf's assignment covers all of the second's
+    Contains,
+}
+
+// Two elves in the roster given some of the same sections, as indices into the roster
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Conflict {
+    first: usize,
+    second: usize,
+    relation: Relation,
+}
+
+// Sweep the assignments in order of start, keeping a heap of the ones still open.
+// Everything open when an assignment starts overlaps it, so the cost is
+// O(n log n) plus the number of conflicts found.
+fn conflicts(elves: &[Elf]) -> Vec<Conflict> {
+    let mut order: Vec<usize> = (0..elves.len()).collect();
+    // Longer assignments first on a tie so they are the ones found containing
+    order.sort_by_key(|&index| (elves[index].sections.start(), Reverse(elves[index].sections.end())));
+    let mut open: BinaryHeap<Reverse<(u32, usize)>> = BinaryHeap::new();
+    let mut conflicts = Vec::new();
+    for index in order {
+        let sections = elves[index].sections;
+        while open.peek().is_some_and(|Reverse((end, _))| *end < sections.start()) {
+            open.pop();
+        }
+        for &Reverse((end, other)) in open.iter() {
+            let relation = if end >= sections.end() { Relation::Contains } else { Relation::Overlaps };
+            conflicts.push(Conflict { first: other, second: index, relation });
+        }
+        open.push(Reverse((sections.end(), index)));
     }
+    conflicts.sort();
+    conflicts
 }
 
+// How many elves are on each section, as runs of sections sharing the same count.
+// Sections nobody covers are left out.
+fn coverage_depth(elves: &[Elf]) -> Vec<(Interval, usize)> {
+    // Ends are exclusive here so the last section can't overflow
+    let mut events: Vec<(u64, isize)> = elves.iter()
+        .flat_map(|elf| [(elf.sections.start() as u64, 1), (elf.sections.end() as u64 + 1, -1)])
+        .collect();
+    events.sort();
+    let mut depths: Vec<(Interval, usize)> = Vec::new();
+    let mut depth: isize = 0;
+    let mut previous = 0;
+    for (position, change) in events {
+        if position > previous && depth > 0 {
+            let sections = Interval::new(previous as u32, (position - 1) as u32).unwrap();
+            match depths.last_mut() {
+                Some((last, last_depth)) if *last_depth == depth as usize && last.end() + 1 == sections.start() => {
+                    last.extend(sections.end());
+                },
+                _ => depths.push((sections, depth as usize)),
+            }
+        }
+        depth += change;
+        previous = position;
+    }
+    depths
+}
+
+fn conflict_graph(elves: &[Elf], conflicts: &[Conflict]) -> String {
+    let mut dot = String::from("graph roster {\n");
+    for elf in elves {
+        dot.push_str(&format!("    \"{}\" [label=\"{}\\n{}-{}\"];\n", elf, elf, elf.sections.start(), elf.sections.end()));
+    }
+    for conflict in conflicts {
+        let style = match conflict.relation {
+            Relation::Overlaps => "dashed",
+            Relation::Contains => "solid",
+        };
+        dot.push_str(&format!("    \"{}\" -- \"{}\" [style={}];\n", elves[conflict.first], elves[conflict.second], style));
+    }
+    dot.push_str("}\n");
+    dot
+}
 
 fn solve1(input: &str) -> u32 {
-    return input
-        .lines()
-        .map(|x| new_pair(x.trim()).is_subset())
-        .filter(|x| *x)
-        .count() as u32;
+    parse_pairs(input).iter().filter(|pair| pair.is_subset()).count() as u32
 }
 
 fn solve2(input: &str) -> u32 {
-    return input
-        .lines()
-        .map(|x| new_pair(x.trim()).range_overlaps())
-        .filter(|x| *x)
-        .count() as u32;
+    parse_pairs(input).iter().filter(|pair| pair.range_overlaps()).count() as u32
 }
 
 
@@ -68,4 +236,64 @@ mod tests {
         let solution_2 = solve2(&input);
         assert_eq!(solution_2, 4);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn can_find_gaps_and_duplicates() {
+        let pairs = parse_pairs(&problem::load_test(4));
+        assert!(uncovered(&pairs).is_empty());
+        assert_eq!(duplicated(&pairs), 1 + 5 + 1 + 3);
+
+        let pairs = parse_pairs("2-4,10-12\n3-5,20-20");
+        let gaps: Vec<Interval> = uncovered(&pairs).intervals().to_vec();
+        assert_eq!(gaps, vec![Interval::new(6, 9).unwrap(), Interval::new(13, 19).unwrap()]);
+        assert_eq!(duplicated(&pairs), 0);
+    }
+
+    // Compare every assignment with every other
+    fn all_conflicts(elves: &[Elf]) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        for (i, a) in elves.iter().enumerate() {
+            for (j, b) in elves.iter().enumerate().skip(i + 1) {
+                if !a.sections.overlaps(&b.sections) {
+                    continue;
+                }
+                // Same order as the sweep, so the containing elf comes first
+                let key = |index: usize| (elves[index].sections.start(), Reverse(elves[index].sections.end()), index);
+                let (first, second) = if key(i) < key(j) { (i, j) } else { (j, i) };
+                let relation = if elves[first].sections.end() >= elves[second].sections.end() {
+                    Relation::Contains
+                } else {
+                    Relation::Overlaps
+                };
+                conflicts.push(Conflict { first, second, relation });
+            }
+        }
+        conflicts.sort();
+        conflicts
+    }
+
+    #[test]
+    fn can_sweep_roster() {
+        let elves = roster(&parse_pairs(&problem::load_test(4)));
+        assert_eq!(elves[3], Elf { line: 2, side: 2, sections: Interval::new(4, 5).unwrap() });
+        let found = conflicts(&elves);
+        assert_eq!(found, all_conflicts(&elves));
+        // 2-8 holds 3-7, which holds 6-6
+        assert!(found.contains(&Conflict { first: 6, second: 7, relation: Relation::Contains }));
+        assert!(found.contains(&Conflict { first: 7, second: 8, relation: Relation::Contains }));
+
+        let depths = coverage_depth(&elves);
+        for section in 1..=10 {
+            let expected = elves.iter().filter(|elf| elf.sections.contains(section)).count();
+            let found = depths.iter()
+                .find(|(sections, _)| sections.contains(section))
+                .map_or(0, |&(_, depth)| depth);
+            assert_eq!(found, expected, "section {}", section);
+        }
+        assert_eq!(depths.iter().map(|&(_, depth)| depth).max(), Some(8));
+
+        let graph = conflict_graph(&elves, &found);
+        assert!(graph.contains("\"4.1\" -- \"4.2\" [style=solid];"));
+        assert_eq!(graph.matches(" -- ").count(), found.len());
+    }
+}
@@ -1,48 +1,270 @@
 use aoc2022_rust::problem;
-use std::collections::HashSet;
+use clap::Parser;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::io::BufRead;
+use std::path::PathBuf;
+
+/// Find where the start-of-packet and start-of-message markers end
+#[derive(Parser, Debug)]
+struct Cli {
+    /// Number of distinct characters making a marker, repeat for several
+    #[arg(short, long, default_values_t = [4, 14], value_parser = clap::value_parser!(u32).range(1..))]
+    window: Vec<u32>,
+    /// Report every position a marker ends rather than only the first
+    #[arg(short, long)]
+    all: bool,
+    /// Read the datastream from this file instead of the puzzle input, - for stdin
+    #[arg(short, long)]
+    input: Option<PathBuf>,
+}
 
 fn main() {
-    let input = problem::load(6);
-    let solution_1 = solve1(&input);
-    println!("Puzzle 1 {}", solution_1);
-    let solution_2 = solve2(&input);
-    println!("Puzzle 2 {}", solution_2);
+    let args = Cli::parse();
+    let reader = problem::open_input(6, args.input.as_deref());
+    let windows: Vec<usize> = args.window.iter().map(|&n| n as usize).collect();
+    let mut found: Vec<Vec<usize>> = vec![Vec::new(); windows.len()];
+    for marker in markers(reader, &windows) {
+        let marker = match marker {
+            Ok(marker) => marker,
+            Err(error) => {
+                eprintln!("{}", error);
+                return;
+            }
+        };
+        if args.all || found[marker.window].is_empty() {
+            found[marker.window].push(marker.position);
+        }
+        // No need to read the rest of the stream once every window has a marker
+        if !args.all && found.iter().all(|positions| !positions.is_empty()) {
+            break;
+        }
+    }
+    // The puzzles' own windows are reported like every other day
+    let puzzles = !args.all && args.window == [4, 14];
+    for (index, (window, positions)) in args.window.iter().zip(found).enumerate() {
+        if puzzles {
+            match positions.first() {
+                Some(position) => println!("Puzzle {} {}", index + 1, position),
+                None => println!("Puzzle {} no marker", index + 1),
+            }
+        } else if positions.is_empty() {
+            println!("Window {}: no marker", window);
+        } else {
+            let positions: Vec<String> = positions.iter().map(|position| position.to_string()).collect();
+            println!("Window {}: {}", window, positions.join(", "));
+        }
+    }
 }
 
+// Decodes a stream a buffer at a time, leaving out the line breaks. A character
+// split across two buffers is carried over until the rest of it arrives.
+struct Chars<R> {
+    reader: R,
+    bytes: Vec<u8>,
+    chunk: String,
+    offset: usize,
+    // Set after an error so the stream isn't read past it
+    failed: bool,
+}
+
+impl<R: BufRead> Chars<R> {
+    fn new(reader: R) -> Self {
+        Chars { reader, bytes: Vec::new(), chunk: String::new(), offset: 0, failed: false }
+    }
 
-fn characters_unique(characters: &str, n: usize) -> bool {
-    let chars: HashSet<char> = characters.chars().collect();
-    chars.len() == n
+    // Decodes the next buffer into `chunk`, returning false at the end of the stream
+    fn refill(&mut self) -> io::Result<bool> {
+        let buffer = loop {
+            match self.reader.fill_buf() {
+                Ok(buffer) => break buffer,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        };
+        if buffer.is_empty() {
+            return if self.bytes.is_empty() {
+                Ok(false)
+            } else {
+                Err(io::Error::new(io::ErrorKind::InvalidData, "Stream ends part way through a character"))
+            };
+        }
+        self.bytes.extend_from_slice(buffer);
+        let read = buffer.len();
+        self.reader.consume(read);
+        let valid = match std::str::from_utf8(&self.bytes) {
+            Ok(text) => text.len(),
+            // Only an incomplete character at the very end can be finished by the next buffer
+            Err(error) if error.error_len().is_none() => error.valid_up_to(),
+            Err(error) => return Err(io::Error::new(io::ErrorKind::InvalidData, error)),
+        };
+        self.chunk.clear();
+        self.chunk.push_str(std::str::from_utf8(&self.bytes[..valid]).unwrap());
+        self.offset = 0;
+        self.bytes.drain(..valid);
+        Ok(true)
+    }
 }
 
-fn solve(input: &str, n: usize) -> usize{
-    for x in n..input.len() {
-        if characters_unique(&input[(x-n)..x], n) {
-            return x
+impl<R: BufRead> Iterator for Chars<R> {
+    type Item = io::Result<char>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.chunk[self.offset..].chars().next() {
+                Some(character) => {
+                    self.offset += character.len_utf8();
+                    if character != '\n' && character != '\r' {
+                        return Some(Ok(character));
+                    }
+                }
+                None if self.failed => return None,
+                None => match self.refill() {
+                    Ok(true) => {}
+                    Ok(false) => return None,
+                    Err(error) => {
+                        self.failed = true;
+                        return Some(Err(error));
+                    }
+                },
+            }
         }
     }
-    return 0
 }
 
+// Keeps the last `window` characters with a count of each, and how many of the
+// counts are above one, so each character is handled in constant time.
+struct Detector {
+    window: usize,
+    recent: VecDeque<char>,
+    counts: HashMap<char, usize>,
+    repeated: usize,
+    // Characters seen so far
+    position: usize,
+}
 
-fn solve1(input: &str) -> usize {
-    solve(input, 4)
+impl Detector {
+    fn new(window: usize) -> Self {
+        Detector { window, recent: VecDeque::with_capacity(window + 1), counts: HashMap::new(), repeated: 0, position: 0 }
+    }
+
+    // Returns the number of characters read so far if the last `window` are all different
+    fn push(&mut self, character: char) -> Option<usize> {
+        self.position += 1;
+        self.recent.push_back(character);
+        let count = self.counts.entry(character).or_insert(0);
+        *count += 1;
+        if *count == 2 {
+            self.repeated += 1;
+        }
+        if self.recent.len() > self.window {
+            let oldest = self.recent.pop_front().unwrap();
+            let count = self.counts.get_mut(&oldest).unwrap();
+            *count -= 1;
+            if *count == 1 {
+                self.repeated -= 1;
+            }
+        }
+        (self.recent.len() == self.window && self.repeated == 0).then_some(self.position)
+    }
 }
 
-fn solve2(input: &str) -> usize {
-    solve(input, 14)
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Marker {
+    // Index into the windows being searched for
+    window: usize,
+    // Characters read when the marker ends, counting from 1
+    position: usize,
+}
+
+// Every marker in the stream for each window size, all found in the one pass
+fn markers<R: BufRead>(reader: R, windows: &[usize]) -> impl Iterator<Item = io::Result<Marker>> {
+    let mut detectors: Vec<Detector> = windows.iter().map(|&n| Detector::new(n)).collect();
+    Chars::new(reader).flat_map(move |character| {
+        let found: Vec<io::Result<Marker>> = match character {
+            Ok(character) => detectors.iter_mut().enumerate()
+                .filter_map(|(window, detector)| detector.push(character).map(|position| Ok(Marker { window, position })))
+                .collect(),
+            Err(error) => vec![Err(error)],
+        };
+        found
+    })
 }
 
 
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use aoc2022_rust::random::Lcg;
+    use std::collections::HashSet;
+    use std::io::BufReader;
+
+    fn solve(input: &str, n: usize) -> Option<usize> {
+        // Read through a normal sized buffer rather than the whole string at once
+        markers(BufReader::new(input.as_bytes()), &[n]).next()
+            .map(|marker| marker.expect("Reading from a string can't fail").position)
+    }
+
+    // The original, checking a fresh set for every window
+    fn solve_sets(input: &str, n: usize) -> usize {
+        for x in n..input.len() {
+            let chars: HashSet<char> = input[(x - n)..x].chars().collect();
+            if chars.len() == n {
+                return x
+            }
+        }
+        0
+    }
+
     #[test]
     fn it_works() {
         let input = problem::load_test(6);
-        let solution_1 = solve1(&input);
-        assert_eq!(solution_1, 7);
-        let solution_2 = solve2(&input);
-        assert_eq!(solution_2, 19);
+        let solution_1 = solve(&input, 4);
+        assert_eq!(solution_1, Some(7));
+        let solution_2 = solve(&input, 14);
+        assert_eq!(solution_2, Some(19));
+    }
+
+    #[test]
+    fn can_find_every_marker() {
+        assert_eq!(solve("aaaa", 2), None);
+        assert_eq!(solve("", 1), None);
+        // A marker right at the end of the stream is still found
+        assert_eq!(solve("aabc", 3), Some(4));
+        // Characters are counted rather than bytes
+        assert_eq!(solve("ééab", 3), Some(4));
+        assert_eq!(solve("ab\r\ncd", 4), Some(4));
+        // A buffer of one byte splits every accented character in two
+        let split = BufReader::with_capacity(1, "aéébcé".as_bytes());
+        let found: Vec<usize> = markers(split, &[3]).map(|marker| marker.unwrap().position).collect();
+        assert_eq!(found, vec![5, 6]);
+        let truncated = BufReader::with_capacity(1, &"abé".as_bytes()[..3]);
+        assert_eq!(markers(truncated, &[2]).last().unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let all: Vec<Marker> = markers("abcabbc".as_bytes(), &[3, 2]).collect::<io::Result<_>>().unwrap();
+        let positions = |window| all.iter().filter(|marker| marker.window == window).map(|marker| marker.position).collect::<Vec<_>>();
+        assert_eq!(positions(0), vec![3, 4, 5]);
+        assert_eq!(positions(1), vec![2, 3, 4, 5, 7]);
     }
-}
\ No newline at end of file
+
+    #[test]
+    #[ignore]
+    fn bench_streaming_against_sets() {
+        let mut random = Lcg::new(2022);
+        let input: String = (0..1_000_000)
+            .map(|_| (b'a' + random.below(26) as u8) as char)
+            .collect();
+        // Only the longest window's marker is far into the stream
+        for n in [4, 14, 20] {
+            let start = std::time::Instant::now();
+            let by_set = solve_sets(&input, n);
+            let set_time = start.elapsed();
+
+            let start = std::time::Instant::now();
+            let streamed = solve(&input, n);
+            let stream_time = start.elapsed();
+
+            println!("Window {}: sets {:?}, streaming {:?}", n, set_time, stream_time);
+            assert_eq!(streamed, Some(by_set));
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use std::cmp::{max, min};
+
+// An inclusive range of integers. The fields are private so start <= end always holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Interval {
+    start: u32,
+    end: u32,
+}
+
+impl Interval {
+    // None if the interval would be empty
+    pub fn new(start: u32, end: u32) -> Option<Interval> {
+        (start <= end).then_some(Interval { start, end })
+    }
+
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    pub fn end(&self) -> u32 {
+        self.end
+    }
+
+    // Moves the end out to include end, an end before the current one leaves it alone
+    pub fn extend(&mut self, end: u32) {
+        self.end = max(self.end, end);
+    }
+
+    // Number of integers in the interval, which for 0 to u32::MAX doesn't fit in a u32
+    pub fn size(&self) -> u64 {
+        (self.end - self.start) as u64 + 1
+    }
+
+    pub fn contains(&self, value: u32) -> bool {
+        self.start <= value && value <= self.end
+    }
+
+    pub fn contains_interval(&self, other: &Interval) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    pub fn intersection(&self, other: &Interval) -> Option<Interval> {
+        Interval::new(max(self.start, other.start), min(self.end, other.end))
+    }
+
+    // Overlapping or next to each other with no integer between
+    fn touches(&self, other: &Interval) -> bool {
+        self.start <= other.end.saturating_add(1) && other.start <= self.end.saturating_add(1)
+    }
+}
+
+// A set of integers kept as sorted intervals that neither overlap nor touch, so
+// each set has exactly one representation and equal sets compare equal
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct IntervalSet {
+    intervals: Vec<Interval>,
+}
+
+impl IntervalSet {
+    pub fn new() -> Self {
+        IntervalSet { intervals: vec![] }
+    }
+
+    pub fn intervals(&self) -> &[Interval] {
+        &self.intervals
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    // The smallest interval covering the whole set
+    pub fn span(&self) -> Option<Interval> {
+        Some(Interval { start: self.intervals.first()?.start, end: self.intervals.last()?.end })
+    }
+
+    // Number of integers in the set
+    pub fn coverage(&self) -> u64 {
+        self.intervals.iter().map(Interval::size).sum()
+    }
+
+    pub fn insert(&mut self, interval: Interval) {
+        // Intervals before first end before the new one starts, those from last on
+        // start after it ends, and everything in between merges with it
+        let first = self.intervals.partition_point(|other| other.end.saturating_add(1) < interval.start);
+        let last = self.intervals.partition_point(|other| other.start <= interval.end.saturating_add(1));
+        let merged = self.intervals[first..last].iter().fold(interval, |merged, other| Interval {
+            start: min(merged.start, other.start),
+            end: max(merged.end, other.end),
+        });
+        self.intervals.splice(first..last, [merged]);
+    }
+
+    pub fn contains(&self, value: u32) -> bool {
+        let index = self.intervals.partition_point(|interval| interval.end < value);
+        self.intervals.get(index).is_some_and(|interval| interval.contains(value))
+    }
+
+    pub fn contains_interval(&self, interval: &Interval) -> bool {
+        let index = self.intervals.partition_point(|other| other.end < interval.start);
+        self.intervals.get(index).is_some_and(|other| other.contains_interval(interval))
+    }
+
+    pub fn is_superset(&self, other: &IntervalSet) -> bool {
+        other.intervals.iter().all(|interval| self.contains_interval(interval))
+    }
+
+    pub fn overlaps(&self, other: &IntervalSet) -> bool {
+        !self.intersection(other).is_empty()
+    }
+
+    pub fn union(&self, other: &IntervalSet) -> IntervalSet {
+        let mut intervals: Vec<Interval> = Vec::with_capacity(self.intervals.len() + other.intervals.len());
+        let mut merged = self.intervals.iter().chain(other.intervals.iter()).copied().collect::<Vec<Interval>>();
+        merged.sort();
+        for interval in merged {
+            match intervals.last_mut() {
+                Some(last) if last.touches(&interval) => last.extend(interval.end),
+                _ => intervals.push(interval),
+            }
+        }
+        IntervalSet { intervals }
+    }
+
+    pub fn intersection(&self, other: &IntervalSet) -> IntervalSet {
+        let mut intervals = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < self.intervals.len() && j < other.intervals.len() {
+            let (a, b) = (self.intervals[i], other.intervals[j]);
+            intervals.extend(a.intersection(&b));
+            // Whichever ends first can't meet anything further on in the other set
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet { intervals }
+    }
+
+    pub fn difference(&self, other: &IntervalSet) -> IntervalSet {
+        let mut intervals = vec![];
+        let mut j = 0;
+        for &interval in self.intervals.iter() {
+            // What's left of the interval once the parts in other before it are removed
+            let mut start = Some(interval.start);
+            while let Some(from) = start {
+                while j < other.intervals.len() && other.intervals[j].end < from {
+                    j += 1;
+                }
+                match other.intervals.get(j) {
+                    Some(cut) if cut.start <= interval.end => {
+                        if from < cut.start {
+                            intervals.push(Interval { start: from, end: cut.start - 1 });
+                        }
+                        start = cut.end.checked_add(1).filter(|&next| next <= interval.end);
+                    }
+                    _ => {
+                        intervals.push(Interval { start: from, end: interval.end });
+                        start = None;
+                    }
+                }
+            }
+        }
+        IntervalSet { intervals }
+    }
+}
+
+impl From<Interval> for IntervalSet {
+    fn from(interval: Interval) -> Self {
+        IntervalSet { intervals: vec![interval] }
+    }
+}
+
+impl FromIterator<Interval> for IntervalSet {
+    fn from_iter<I: IntoIterator<Item = Interval>>(iter: I) -> Self {
+        let mut set = IntervalSet::new();
+        for interval in iter {
+            set.insert(interval);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interval::*;
+    use crate::random::Lcg;
+
+    fn interval(start: u32, end: u32) -> Interval {
+        Interval::new(start, end).unwrap()
+    }
+
+    fn set(intervals: &[(u32, u32)]) -> IntervalSet {
+        intervals.iter().map(|&(start, end)| interval(start, end)).collect()
+    }
+
+    #[test]
+    fn can_compare_intervals() {
+        assert_eq!(Interval::new(3, 2), None);
+        assert_eq!(interval(2, 4).size(), 3);
+        assert_eq!(interval(0, u32::MAX).size(), 1 << 32);
+        assert!(interval(2, 8).contains_interval(&interval(3, 7)));
+        assert!(!interval(3, 7).contains_interval(&interval(2, 8)));
+        assert!(interval(5, 7).overlaps(&interval(7, 9)));
+        assert!(!interval(2, 3).overlaps(&interval(4, 5)));
+        assert_eq!(interval(2, 6).intersection(&interval(4, 8)), Some(interval(4, 6)));
+        assert_eq!(interval(2, 3).intersection(&interval(4, 5)), None);
+
+        let mut grown = interval(4, 6);
+        grown.extend(9);
+        assert_eq!((grown.start(), grown.end()), (4, 9));
+        // Ending before the start can't flip the interval round
+        grown.extend(1);
+        assert_eq!(grown, interval(4, 9));
+    }
+
+    #[test]
+    fn sets_are_normalised() {
+        // Overlapping and touching intervals merge, in any order
+        let merged = set(&[(10, 12), (1, 3), (4, 5), (7, 8), (2, 2), (8, 10)]);
+        assert_eq!(merged.intervals(), &[interval(1, 5), interval(7, 12)]);
+        assert_eq!(merged, set(&[(7, 12), (1, 5)]));
+        assert_eq!(merged.coverage(), 11);
+        assert_eq!(merged.span(), Some(interval(1, 12)));
+        assert_eq!(IntervalSet::new().span(), None);
+        assert!(merged.contains(1) && merged.contains(5) && merged.contains(9));
+        assert!(!merged.contains(0) && !merged.contains(6) && !merged.contains(13));
+        assert_eq!(set(&[(0, 5), (6, u32::MAX)]).intervals(), &[interval(0, u32::MAX)]);
+    }
+
+    #[test]
+    fn can_combine_sets() {
+        let a = set(&[(1, 5), (10, 20), (30, 30)]);
+        let b = set(&[(4, 12), (18, 25), (31, 40)]);
+        assert_eq!(a.union(&b), set(&[(1, 25), (30, 40)]));
+        assert_eq!(a.intersection(&b), set(&[(4, 5), (10, 12), (18, 20)]));
+        assert_eq!(a.difference(&b), set(&[(1, 3), (13, 17), (30, 30)]));
+        assert_eq!(b.difference(&a), set(&[(6, 9), (21, 25), (31, 40)]));
+        assert!(a.overlaps(&b));
+        assert!(!a.overlaps(&set(&[(6, 9), (21, 29)])));
+        assert_eq!(a.difference(&IntervalSet::new()), a);
+        assert_eq!(a.difference(&set(&[(0, u32::MAX)])), IntervalSet::new());
+        assert_eq!(set(&[(0, u32::MAX)]).difference(&set(&[(0, 0), (u32::MAX, u32::MAX)])), set(&[(1, u32::MAX - 1)]));
+
+        assert!(a.is_superset(&set(&[(2, 4), (15, 20)])));
+        assert!(!a.is_superset(&set(&[(2, 6)])));
+        assert!(a.contains_interval(&interval(10, 20)));
+        assert!(!a.contains_interval(&interval(5, 10)));
+    }
+
+    #[test]
+    fn set_operations_match_brute_force() {
+        let mut random = Lcg::new(2022);
+        let mut random_set = || {
+            (0..6)
+                .map(|_| {
+                    let start = random.below(60) as u32;
+                    interval(start, start + random.below(8) as u32)
+                })
+                .collect::<IntervalSet>()
+        };
+        for _ in 0..100 {
+            let (a, b) = (random_set(), random_set());
+            for value in 0..80 {
+                let (in_a, in_b) = (a.contains(value), b.contains(value));
+                assert_eq!(a.union(&b).contains(value), in_a || in_b);
+                assert_eq!(a.intersection(&b).contains(value), in_a && in_b);
+                assert_eq!(a.difference(&b).contains(value), in_a && !in_b);
+            }
+            for result in [a.union(&b), a.intersection(&b), a.difference(&b)] {
+                assert!(result.intervals().windows(2).all(|pair| !pair[0].touches(&pair[1]) && pair[0] < pair[1]));
+            }
+        }
+    }
+}
@@ -1 +1,4 @@
-pub mod problem;
\ No newline at end of file
+pub mod interval;
+pub mod ocr;
+pub mod problem;
+pub mod random;
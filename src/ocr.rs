@@ -0,0 +1,193 @@
+use std::fmt;
+
+// Letters used by puzzles that draw their answer on a 6 pixel high display
+const SMALL_GLYPHS: [(char, &str); 18] = [
+    ('A', ".##.\n#..#\n#..#\n####\n#..#\n#..#"),
+    ('B', "###.\n#..#\n###.\n#..#\n#..#\n###."),
+    ('C', ".##.\n#..#\n#...\n#...\n#..#\n.##."),
+    ('E', "####\n#...\n###.\n#...\n#...\n####"),
+    ('F', "####\n#...\n###.\n#...\n#...\n#..."),
+    ('G', ".##.\n#..#\n#...\n#.##\n#..#\n.###"),
+    ('H', "#..#\n#..#\n####\n#..#\n#..#\n#..#"),
+    ('I', "###\n.#.\n.#.\n.#.\n.#.\n###"),
+    ('J', "..##\n...#\n...#\n...#\n#..#\n.##."),
+    ('K', "#..#\n#.#.\n##..\n#.#.\n#.#.\n#..#"),
+    ('L', "#...\n#...\n#...\n#...\n#...\n####"),
+    ('O', ".##.\n#..#\n#..#\n#..#\n#..#\n.##."),
+    ('P', "###.\n#..#\n#..#\n###.\n#...\n#..."),
+    ('R', "###.\n#..#\n#..#\n###.\n#.#.\n#..#"),
+    ('S', ".###\n#...\n#...\n.##.\n...#\n###."),
+    ('U', "#..#\n#..#\n#..#\n#..#\n#..#\n.##."),
+    ('Y', "#...#\n#...#\n.#.#.\n..#..\n..#..\n..#.."),
+    ('Z', "####\n...#\n..#.\n.#..\n#...\n####"),
+];
+
+// Letters used by puzzles that draw their answer on a 10 pixel high display
+const LARGE_GLYPHS: [(char, &str); 15] = [
+    ('A', "..##..\n.#..#.\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#"),
+    ('B', "#####.\n#....#\n#....#\n#....#\n#####.\n#....#\n#....#\n#....#\n#....#\n#####."),
+    ('C', ".####.\n#....#\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#....#\n.####."),
+    ('E', "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n######"),
+    ('F', "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n#....."),
+    ('G', ".####.\n#....#\n#.....\n#.....\n#.....\n#..###\n#....#\n#....#\n#...##\n.###.#"),
+    ('H', "#....#\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#\n#....#"),
+    ('J', "...###\n....#.\n....#.\n....#.\n....#.\n....#.\n....#.\n#...#.\n#...#.\n.###.."),
+    ('K', "#....#\n#...#.\n#..#..\n#.#...\n##....\n##....\n#.#...\n#..#..\n#...#.\n#....#"),
+    ('L', "#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n######"),
+    ('N', "#....#\n##...#\n##...#\n#.#..#\n#.#..#\n#..#.#\n#..#.#\n#...##\n#...##\n#....#"),
+    ('P', "#####.\n#....#\n#....#\n#....#\n#####.\n#.....\n#.....\n#.....\n#.....\n#....."),
+    ('R', "#####.\n#....#\n#....#\n#....#\n#####.\n#..#..\n#...#.\n#...#.\n#....#\n#....#"),
+    ('X', "#....#\n#....#\n.#..#.\n.#..#.\n..##..\n..##..\n.#..#.\n.#..#.\n#....#\n#....#"),
+    ('Z', "######\n.....#\n.....#\n....#.\n...#..\n..#...\n.#....\n#.....\n#.....\n######"),
+];
+
+#[derive(Debug, PartialEq)]
+pub struct UnknownGlyph {
+    // Position of the glyph in the decoded text
+    pub index: usize,
+    // Pixel column of the glyph's left edge in the image
+    pub column: usize,
+    // The glyph's pixels drawn with '#' and '.'
+    pub glyph: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum OcrError {
+    // Only the 6 and 10 pixel high fonts are known
+    UnsupportedHeight(usize),
+    // Some glyphs didn't match a known letter, text has '?' in their place
+    UnknownGlyphs { text: String, glyphs: Vec<UnknownGlyph> },
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrError::UnsupportedHeight(height) => {
+                write!(f, "No font is {} pixels high, expected 6 or 10", height)
+            }
+            OcrError::UnknownGlyphs { text, glyphs } => {
+                write!(f, "Failed to recognise all of {}", text)?;
+                for glyph in glyphs {
+                    write!(f, "\nglyph {} at column {}:\n{}", glyph.index, glyph.column, glyph.glyph)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+// Parses an image drawn with '#' for lit pixels, any other character is unlit.
+// Rows may be ragged, missing pixels are treated as unlit.
+pub fn parse_str(image: &str) -> Result<String, OcrError> {
+    let pixels: Vec<Vec<bool>> = image.lines()
+        .map(|line| line.chars().map(|c| c == '#').collect())
+        .collect();
+    parse_pixels(&pixels)
+}
+
+// Parses a grid of pixels indexed by row then column, true for lit. Letters are
+// separated by fully unlit columns.
+pub fn parse_pixels(pixels: &[Vec<bool>]) -> Result<String, OcrError> {
+    let glyphs: &[(char, &str)] = match pixels.len() {
+        6 => &SMALL_GLYPHS,
+        10 => &LARGE_GLYPHS,
+        height => return Err(OcrError::UnsupportedHeight(height)),
+    };
+    let width = pixels.iter().map(|row| row.len()).max().unwrap_or(0);
+    let is_lit = |row: usize, column: usize| *pixels[row].get(column).unwrap_or(&false);
+    let column_lit = |column: usize| (0..pixels.len()).any(|row| is_lit(row, column));
+
+    let mut text = String::new();
+    let mut unknown = vec![];
+    let mut column = 0;
+    while column < width {
+        if !column_lit(column) {
+            column += 1;
+            continue;
+        }
+        let start = column;
+        while column < width && column_lit(column) {
+            column += 1;
+        }
+        let glyph = (0..pixels.len())
+            .map(|row| (start..column)
+                .map(|c| if is_lit(row, c) { '#' } else { '.' })
+                .collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n");
+        match glyphs.iter().find(|(_, known)| *known == glyph) {
+            Some((letter, _)) => text.push(*letter),
+            None => {
+                unknown.push(UnknownGlyph { index: text.chars().count(), column: start, glyph });
+                text.push('?');
+            }
+        }
+    }
+
+    if unknown.is_empty() {
+        Ok(text)
+    } else {
+        Err(OcrError::UnknownGlyphs { text, glyphs: unknown })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ocr::*;
+
+    #[test]
+    fn can_parse_small_font() {
+        let image = "\
+###..#..#.#....#..#...##..##..####..##..
+#..#.#..#.#....#..#....#.#..#....#.#..#.
+#..#.####.#....####....#.#......#..#..#.
+###..#..#.#....#..#....#.#.##..#...####.
+#....#..#.#....#..#.#..#.#..#.#....#..#.
+#....#..#.####.#..#..##...###.####.#..#.";
+        assert_eq!(parse_str(image), Ok(String::from("PHLHJGZA")));
+    }
+
+    #[test]
+    fn every_glyph_round_trips() {
+        for glyphs in [&SMALL_GLYPHS[..], &LARGE_GLYPHS[..]] {
+            let letters: String = glyphs.iter().map(|(letter, _)| *letter).collect();
+            let rows: Vec<Vec<&str>> = glyphs.iter().map(|(_, glyph)| glyph.lines().collect()).collect();
+            let image = (0..rows[0].len())
+                .map(|row| rows.iter().map(|glyph| glyph[row]).collect::<Vec<&str>>().join(".."))
+                .collect::<Vec<String>>()
+                .join("\n");
+            assert_eq!(parse_str(&image), Ok(letters));
+        }
+    }
+
+    #[test]
+    fn can_parse_ragged_rows() {
+        let image = "\
+#..#
+#..#
+####
+#..#
+#..#
+#..#..###
+";
+        // The trailing row pokes out into a glyph that only has one lit row
+        let error = parse_str(image).unwrap_err();
+        assert_eq!(error, OcrError::UnknownGlyphs {
+            text: String::from("H?"),
+            glyphs: vec![UnknownGlyph {
+                index: 1,
+                column: 6,
+                glyph: String::from("...\n...\n...\n...\n...\n###"),
+            }],
+        });
+        assert_eq!(parse_str(&image.replace("..###", "")), Ok(String::from("H")));
+    }
+
+    #[test]
+    fn rejects_unknown_heights() {
+        assert_eq!(parse_str("#\n#\n#"), Err(OcrError::UnsupportedHeight(3)));
+        assert_eq!(parse_str("").unwrap_err().to_string(), "No font is 0 pixels high, expected 6 or 10");
+    }
+}
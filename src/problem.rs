@@ -1,4 +1,8 @@
 use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
 
 pub fn load(day: u32) -> String {
     load_raw(day).trim().replace('\r', "")
@@ -16,4 +20,22 @@ pub fn load_test(day: u32) -> String {
 pub fn load_raw_test(day: u32) -> String {
     let file = format!("inputs/test_{:02}", day);
     fs::read_to_string(&file).unwrap_or_else(|_| panic!("Error reading file {}", file))
-}
\ No newline at end of file
+}
+
+// Opens the puzzle input for reading a line at a time rather than all at once
+pub fn open(day: u32) -> BufReader<File> {
+    let file = format!("inputs/{:02}", day);
+    BufReader::new(File::open(&file).unwrap_or_else(|_| panic!("Error reading file {}", file)))
+}
+
+// Opens the given file, stdin for -, or the puzzle input when there is no path
+pub fn open_input(day: u32, path: Option<&Path>) -> Box<dyn BufRead> {
+    match path {
+        Some(path) if path.as_os_str() == "-" => Box::new(io::stdin().lock()),
+        Some(path) => {
+            let file = File::open(path).unwrap_or_else(|_| panic!("Error reading file {}", path.display()));
+            Box::new(BufReader::new(file))
+        }
+        None => Box::new(open(day)),
+    }
+}
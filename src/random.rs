@@ -0,0 +1,25 @@
+// Simple linear congruential generator so test and benchmark inputs are
+// repeatable without pulling in a random number crate. Only tests use it.
+//
+// Benchmarks are ignored tests, run them with
+// cargo test --release --bin <day> -- --ignored --nocapture
+pub struct Lcg {
+    state: u64,
+}
+
+impl Lcg {
+    pub fn new(seed: u64) -> Self {
+        Lcg { state: seed }
+    }
+
+    // The whole state, the high bits are the most random
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        self.state
+    }
+
+    // A value from 0 up to but not including limit
+    pub fn below(&mut self, limit: u64) -> u64 {
+        (self.next_u64() >> 33) % limit
+    }
+}